@@ -4,6 +4,8 @@ use crate::{
     Runtime, RuntimeBuilder, Error,
     Output, Probe, Operation, Id
 };
+use crate::condition::{Condition, All};
+use crate::settings::Settings;
 
 
 /// A measurable, reversible task.
@@ -43,6 +45,11 @@ pub trait Action: Send + Sync {
 pub struct Node {
     action: Arc<dyn Action>,
     deps: Vec<Node>,
+    condition: Option<Arc<dyn Condition>>,
+    name: Option<String>,
+    settings: Settings,
+    scope: Option<String>,
+    hidden: bool,
     pub(crate) id: Id
 }
 
@@ -56,14 +63,101 @@ impl Node {
         Self {
             action,
             deps: Vec::new(),
+            condition: None,
+            name: None,
+            settings: Settings::default(),
+            scope: None,
+            hidden: false,
             id: Id::default()
         }
     }
   
     /// Get the display name of the action.
+    /// 
+    /// This is the name set with [`set_name`], or
+    /// the action's own display name.
+    /// 
+    /// [`set_name`]: #method.set_name
     #[must_use]
     pub fn display_name(&self) -> String {
-        self.action.display_name()
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.action.display_name()
+        }
+    }
+
+    /// Override the display name of the action.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = Some(name.into());
+    }
+  
+    /// Get the settings of the action.
+    #[must_use]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Change the settings of the action.
+    /// 
+    /// Settings left unset are inherited from the
+    /// scope that the action belongs to.
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+  
+    /// Add the name of a scope in front of the
+    /// name of the node.
+    pub(crate) fn enter_scope(&mut self, name: &str) {
+        self.set_name(format!("{name}/{}", self.display_name()));
+
+        self.scope = Some(match &self.scope {
+            Some(inner) => format!("{name}/{inner}"),
+            None => name.to_string()
+        });
+    }
+
+    /// Put a node added at runtime in the same
+    /// scope as the node that added it.
+    ///
+    /// The node inherits the settings, dependencies
+    /// and name prefix that the scope gave to its
+    /// parent.
+    pub(crate) fn adopt(&mut self, parent: &Node) {
+        self.settings.inherit(&parent.settings);
+
+        for dep in &parent.deps {
+            self.requires(dep.clone());
+        }
+
+        if let Some(scope) = &parent.scope {
+            self.enter_scope(scope);
+        }
+    }
+
+    /// Keep the node out of events and reports.
+    ///
+    /// Used for nodes that the runtime adds for
+    /// its own bookkeeping, such as the node that
+    /// stands for a scope.
+    pub(crate) fn hide(&mut self) {
+        self.hidden = true;
+    }
+
+    pub(crate) fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn with_id(action: Arc<dyn Action>, id: Id) -> Self {
+        Self {
+            id,
+            ..Self::new(action)
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn action(&self) -> Arc<dyn Action> {
+        self.action.clone()
     }
   
     pub(crate) fn id(&self) -> Id {
@@ -83,8 +177,39 @@ impl Node {
     }
   
     /// Add a dependency to the action.
+    /// 
+    /// Adding the same dependency twice has no
+    /// effect.
     pub fn requires(&mut self, action: Node) {
-        self.deps.push(action);
+        if self.deps.iter().all(|dep| dep.id != action.id) {
+            self.deps.push(action);
+        }
+    }
+
+    /// Only run the action if a condition is met.
+    /// 
+    /// The condition is evaluated when the action
+    /// is about to run, after its dependencies have
+    /// finished. Any nodes that the condition reads
+    /// from are added as dependencies. Calling this
+    /// more than once requires every condition to
+    /// be met.
+    pub fn run_if<C: Condition + 'static>(&mut self, condition: C) {
+        for dep in condition.dependencies() {
+            self.requires(dep);
+        }
+
+        self.condition = Some(match self.condition.take() {
+            Some(existing) => Arc::new(All::new().and(existing).and(condition)),
+            None => Arc::new(condition)
+        });
+    }
+
+    pub(crate) async fn should_run(&self, ctx: Runtime) -> Result<bool, Error> {
+        match &self.condition {
+            Some(condition) => condition.evaluate(ctx).await,
+            None => Ok(true)
+        }
     }
   
     /// Load the state
@@ -0,0 +1,194 @@
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::error;
+use crate::{
+    Action, Runtime, RuntimeBuilder, Node,
+    Output, Error, ErrorKind, Probe, Operation
+};
+use crate::blocking;
+
+
+/// Use a blocking node in the async runtime.
+///
+/// The returned node keeps the ID of the original,
+/// so dynamic inputs that point at the original
+/// still resolve. Its dependencies are converted
+/// too, and must be added to the runtime as well.
+///
+/// The blocking action runs on Tokio's blocking
+/// thread pool. It sees a copy of the outputs and
+/// state of the async runtime as they were when it
+/// started.
+#[must_use]
+pub fn to_async(node: &blocking::Node) -> Node {
+    let mut converted = Node::with_id(Arc::new(FromBlocking(node.action())), node.id());
+
+    for dep in node.deps() {
+        converted.requires(to_async(dep));
+    }
+
+    converted
+}
+
+/// Use an async node in the blocking runtime.
+///
+/// The returned node keeps the ID of the original,
+/// so dynamic inputs that point at the original
+/// still resolve. Its dependencies are converted
+/// too, and must be added to the runtime as well.
+/// Conditions, names and settings of the async
+/// node are not supported by the blocking runtime,
+/// and are dropped.
+///
+/// The async action is driven by a private
+/// current-thread Tokio runtime, so the blocking
+/// runtime must not itself be called from within
+/// an async context. That Tokio runtime only lives
+/// for a single probe or run, and any tasks the
+/// action spawns are dropped with it.
+///
+/// The blocking runtime has no finalizers and
+/// cannot add actions while it runs. If the action
+/// calls [`Runtime::add_finalizer`] or
+/// [`Runtime::add_action`], its finalizers run
+/// straight away and the call fails with
+/// [`ErrorKind::OperationNotSupported`]. This
+/// rules out actions that leave work running, like
+/// background processes.
+///
+/// [`Runtime::add_finalizer`]: ../struct.Runtime.html#method.add_finalizer
+/// [`Runtime::add_action`]: ../struct.Runtime.html#method.add_action
+/// [`ErrorKind::OperationNotSupported`]: ../enum.ErrorKind.html#variant.OperationNotSupported
+#[must_use]
+pub fn to_blocking(node: &Node) -> blocking::Node {
+    let mut converted = blocking::Node::with_id(Arc::new(FromAsync(node.action())), node.id());
+
+    for dep in node.deps() {
+        converted.requires(&to_blocking(&dep));
+    }
+
+    converted
+}
+
+struct FromBlocking(Arc<dyn blocking::Action>);
+
+impl FromBlocking {
+    async fn call<T, F>(&self, runtime: &Runtime, call: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn blocking::Action, &blocking::Runtime) -> Result<T, Error> + Send + 'static
+    {
+        let detached = blocking::Runtime::detached(
+            runtime.outputs_snapshot().await,
+            runtime.state_map().clone()
+        );
+        let action = self.0.clone();
+
+        tokio::task::spawn_blocking(move || call(action.as_ref(), &detached))
+            .await
+            .map_err(|_| Error::internal("SPAWN_BLOCKING_ERROR"))?
+    }
+}
+
+#[async_trait]
+impl Action for FromBlocking {
+    async fn probe(&self, runtime: Runtime) -> Result<Probe, Error> {
+        self.call(&runtime, |action, runtime| action.probe(runtime)).await
+    }
+
+    async fn run(&self, runtime: Runtime, operation: Operation) -> Result<Option<Output>, Error> {
+        self.call(&runtime, move |action, runtime| action.run(runtime, operation)).await
+    }
+
+    async fn load_state(&self, builder: &mut RuntimeBuilder) {
+        let mut blocking_builder = blocking::RuntimeBuilder::new();
+        self.0.load_state(&mut blocking_builder);
+
+        for (id, state) in blocking_builder.state_map() {
+            builder.insert_raw_state(*id, state.clone());
+        }
+    }
+
+    fn display_name(&self) -> String {
+        self.0.display_name()
+    }
+}
+
+struct FromAsync(Arc<dyn Action>);
+
+impl FromAsync {
+    fn block_on<F: std::future::Future>(future: F) -> Result<F::Output, Error> {
+        let executor = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| Error::internal("EXECUTOR_ERROR").with_source(err))?;
+
+        Ok(executor.block_on(future))
+    }
+
+    /// Call the action with a detached runtime, and
+    /// reject anything it registered that would
+    /// outlive the call.
+    fn call<T, F, Fut>(&self, runtime: &blocking::Runtime, call: F) -> Result<T, Error>
+    where
+        F: FnOnce(Runtime) -> Fut,
+        Fut: Future<Output = Result<T, Error>>
+    {
+        let detached = Runtime::detached(runtime.outputs_snapshot(), runtime.state_map().clone());
+
+        Self::block_on(async {
+            let result = call(detached.clone()).await;
+            let mut rejected = Vec::new();
+
+            if detached.has_added_actions() {
+                rejected.push("add actions");
+            }
+
+            if detached.has_finalizers() {
+                // Nothing would run them later, so clean
+                // up now instead of leaking.
+                let _ = detached.run_finalizers().await;
+                rejected.push("add finalizers");
+            }
+
+            if rejected.is_empty() {
+                return result
+            }
+
+            let err = Error::new(
+                ErrorKind::OperationNotSupported,
+                format!("{} cannot {} in the blocking runtime", self.0.display_name(), rejected.join(" or "))
+            );
+
+            error!("{}", err);
+            result.and(Err(err))
+        })?
+    }
+}
+
+impl blocking::Action for FromAsync {
+    fn probe(&self, runtime: &blocking::Runtime) -> Result<Probe, Error> {
+        self.call(runtime, |runtime| self.0.probe(runtime))
+    }
+
+    fn run(&self, runtime: &blocking::Runtime, operation: Operation) -> Result<Option<Output>, Error> {
+        self.call(runtime, |runtime| self.0.run(runtime, operation))
+    }
+
+    fn load_state(&self, builder: &mut blocking::RuntimeBuilder) {
+        let mut async_builder = RuntimeBuilder::new();
+
+        if Self::block_on(self.0.load_state(&mut async_builder)).is_err() {
+            return;
+        }
+
+        for (id, state) in async_builder.state_map() {
+            builder.insert_raw_state(*id, state.clone());
+        }
+    }
+
+    fn display_name(&self) -> String {
+        self.0.display_name()
+    }
+}
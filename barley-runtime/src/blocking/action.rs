@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use crate::{Id, Probe, Operation};
 use crate::error::Error;
 use crate::output::Output;
@@ -7,8 +8,10 @@ use super::runtime::{Runtime, RuntimeBuilder};
 /// 
 /// This trait is similar to the version at the crate
 /// root, but this one is synchronous. Using this trait
-/// may be useful when multithreading is not desired.
-pub trait Action {
+/// may be useful when an async runtime is not desired.
+/// Actions may be run on worker threads, so they must
+/// be `Send` and `Sync`.
+pub trait Action: Send + Sync {
     /// Run the action.
     /// 
     /// This function should never be called directly.
@@ -57,22 +60,24 @@ pub trait Action {
 /// A node in the workflow graph.
 /// 
 /// Nodes have a list of dependencies, and they can
-/// be run by the runtime.
-pub struct Node<'node> {
-    action: Box<dyn Action>,
-    deps: Vec<&'node Node<'node>>,
+/// be run by the runtime. Cloning a node is cheap,
+/// and the clone refers to the same action.
+#[derive(Clone)]
+pub struct Node {
+    action: Arc<dyn Action>,
+    deps: Vec<Node>,
     pub(crate) id: Id
 }
 
-impl<'node> Node<'node> {
-    /// Create a node from a boxed action.
+impl Node {
+    /// Create a node from a shared action.
     /// 
     /// You should convert your action into a
     /// node using [`From`] when possible.
     /// 
     /// [`From`]: https://doc.rust-lang.org/std/convert/trait.From.html
     #[must_use]
-    pub fn new(action: Box<dyn Action>) -> Self {
+    pub fn new(action: Arc<dyn Action>) -> Self {
         Self {
             action,
             deps: Vec::new(),
@@ -91,12 +96,26 @@ impl<'node> Node<'node> {
         self.action.display_name()
     }
 
+    #[cfg(feature = "async")]
+    pub(crate) fn with_id(action: Arc<dyn Action>, id: Id) -> Self {
+        Self {
+            action,
+            deps: Vec::new(),
+            id
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn action(&self) -> Arc<dyn Action> {
+        self.action.clone()
+    }
+
     pub(crate) fn id(&self) -> Id {
         self.id
     }
 
-    pub(crate) fn deps(&self) -> Vec<&'node Node<'node>> {
-        self.deps.clone()
+    pub(crate) fn deps(&self) -> &[Node] {
+        &self.deps
     }
 
     pub(crate) fn probe(&self, runtime: &Runtime) -> Result<Probe, Error> {
@@ -109,15 +128,12 @@ impl<'node> Node<'node> {
 
     /// Add a dependency to the node.
     ///
-    /// Unlike in the asynchronous version of this
-    /// library, this function takes a reference to
-    /// the node instead of an owned [`Arc`]. This
-    /// is because the blocking runtime does not
-    /// need to worry about multithreading.
-    /// 
-    /// [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
-    pub fn requires(&mut self, action: &'node Node) {
-        self.deps.push(action);
+    /// Adding the same dependency twice has no
+    /// effect.
+    pub fn requires(&mut self, action: &Node) {
+        if self.deps.iter().all(|dep| dep.id != action.id) {
+            self.deps.push(action.clone());
+        }
     }
 
     /// Load the state of the action.
@@ -130,3 +146,12 @@ impl<'node> Node<'node> {
         self.action.load_state(builder);
     }
 }
+
+impl<A> From<A> for Node
+where
+    A: Action + 'static
+{
+    fn from(action: A) -> Self {
+        Self::new(Arc::new(action))
+    }
+}
@@ -5,8 +5,11 @@ use super::action::Node;
 /// 
 /// This enum is used to represent input data to
 /// an action in a workflow. It will be resolved
-/// to a specific type once the workflow is run.
-pub enum Input<'node, T> {
+/// to a specific type once the workflow is run,
+/// using [`Runtime::resolve`].
+/// 
+/// [`Runtime::resolve`]: struct.Runtime.html#method.resolve
+pub enum Input<T> {
     /// A static value.
     Static(T),
     /// An action.
@@ -14,10 +17,10 @@ pub enum Input<'node, T> {
     /// The output of this action will be used as
     /// the input to the action that this input
     /// belongs to.
-    Dynamic(&'node Node<'node>)
+    Dynamic(Node)
 }
 
-impl<'node, T> Input<'node, T> {
+impl<T> Input<T> {
     /// Create a new static input.
     #[must_use]
     pub fn new_static(value: T) -> Self {
@@ -26,7 +29,7 @@ impl<'node, T> Input<'node, T> {
 
     /// Create a new dynamic input.
     #[must_use]
-    pub fn new_dynamic(value: &'node Node<'node>) -> Self {
+    pub fn new_dynamic(value: Node) -> Self {
         Self::Dynamic(value)
     }
 
@@ -45,7 +48,7 @@ impl<'node, T> Input<'node, T> {
     /// 
     /// If the input is static, this will return
     /// `None`.
-    pub fn dynamic(&self) -> Option<&'node Node<'node>> {
+    pub fn dynamic(&self) -> Option<&Node> {
         match self {
             Self::Dynamic(action) => Some(action),
             Self::Static(_) => None
@@ -62,3 +65,9 @@ impl<'node, T> Input<'node, T> {
         self.dynamic().is_some()
     }
 }
+
+impl<T> From<T> for Input<T> {
+    fn from(value: T) -> Self {
+        Self::new_static(value)
+    }
+}
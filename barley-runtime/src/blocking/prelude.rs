@@ -1,12 +1,11 @@
 pub use super::{
     Action, Runtime,
     RuntimeBuilder, Scope,
-    Node
+    Node, Input
 };
 
 pub use crate::{
     Operation, Probe,
-    error::Error,
-    output::Output,
-    input::Input
+    error::{Error, ErrorKind},
+    output::Output
 };
@@ -1,10 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::any::{Any, TypeId};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Instant;
 
 use crate::{Id, Operation};
 use crate::output::Output;
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
+use crate::event::{Event, SkipReason};
 use super::action::Node;
+use super::input::Input;
 use super::scope::Scope;
 
 
@@ -14,115 +20,356 @@ use super::scope::Scope;
 /// all of the actions that need to be run, and it
 /// ensures that all dependencies are run before the
 /// actions that depend on them.
-pub struct Runtime<'run> {
-    ctx: Vec<Node<'run>>,
-    outputs: HashMap<Id, Output>,
-    state: HashMap<TypeId, Box<dyn Any>>
+/// 
+/// By default, actions run one at a time on the
+/// calling thread. See [`RuntimeBuilder::workers`]
+/// to run independent actions in parallel.
+/// 
+/// [`RuntimeBuilder::workers`]: struct.RuntimeBuilder.html#method.workers
+pub struct Runtime {
+    ctx: Vec<Node>,
+    outputs: Mutex<HashMap<Id, Output>>,
+    state: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    subscribers: Mutex<Vec<Sender<Event>>>,
+    workers: usize
+}
+
+/// The progress of a run, shared between the
+/// worker threads.
+#[derive(Default)]
+struct Progress {
+    pending: Vec<usize>,
+    running: usize,
+    done: HashSet<Id>,
+    error: Option<Error>
+}
+
+/// Held by a worker while it runs an action.
+/// 
+/// If the action panics, this stops the run and
+/// wakes the other workers, so they don't wait
+/// for an action that will never finish. The
+/// panic then carries on out of the run.
+struct Running<'a> {
+    progress: &'a Mutex<Progress>,
+    changed: &'a Condvar
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            return;
+        }
+
+        let mut guard = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+        guard.running -= 1;
+        guard.error.get_or_insert_with(|| Error::action_failed("Action panicked"));
+        self.changed.notify_all();
+    }
 }
 
-impl<'run> Runtime<'run> {
+impl Runtime {
     /// Run the workflow.
     /// 
+    /// Actions run once all of their dependencies
+    /// have finished. Actions whose probe reports
+    /// that they do not need to run are skipped.
+    /// 
     /// # Errors
     /// 
-    /// All action errors are handled internally. This
-    /// function will return an error if there is an
+    /// This function will return an error if any of
+    /// the actions fail, if the actions depend on
+    /// each other in a cycle, or if there is an
     /// internal error with the runtime itself.
-    pub fn perform(mut self) -> Result<(), Error> {
-        let actions = &mut self.ctx;
-        let mut dependents: HashMap<Id, usize> = HashMap::new();
-
-        for action in &mut *actions {
-            dependents.insert(action.id, 0);
+    pub fn perform(self) -> Result<(), Error> {
+        let started = Instant::now();
 
-            action.deps()
+        self.emit(&Event::RunStarted {
+            actions: self.ctx
                 .iter()
-                .for_each(|dep| {
-                    let count = dependents.entry(dep.id()).or_insert(0);
-                    *count += 1;
-                });
-        }
+                .map(|action| (action.id, action.display_name()))
+                .collect()
+        });
 
-        actions.sort_by(|a, b| {
-            let a_deps = dependents.get(&a.id).unwrap_or(&0);
-            let b_deps = dependents.get(&b.id).unwrap_or(&0);
+        let result = self.perform_actions();
 
-            a_deps.cmp(b_deps)
+        self.emit(&Event::RunFinished {
+            success: result.is_ok(),
+            elapsed: started.elapsed()
         });
 
-        let actions = &self.ctx;
+        result
+    }
+
+    /// Run the actions on the calling thread, or on
+    /// a pool of worker threads.
+    /// 
+    /// Each worker takes the first action whose
+    /// dependencies have all finished. Once an
+    /// action fails, no new actions are started.
+    fn perform_actions(&self) -> Result<(), Error> {
+        let known = self.ctx.iter().map(|action| action.id).collect::<HashSet<_>>();
+        let progress = Mutex::new(Progress {
+            pending: (0..self.ctx.len()).collect(),
+            ..Progress::default()
+        });
+        let changed = Condvar::new();
+
+        if self.workers > 1 {
+            thread::scope(|scope| {
+                for _ in 0..self.workers.min(self.ctx.len()) {
+                    scope.spawn(|| self.work(&known, &progress, &changed));
+                }
+            });
+        } else {
+            self.work(&known, &progress, &changed);
+        }
 
-        for action in actions {
-            let output = action.run(&self, Operation::Perform)?;
+        let progress = progress.into_inner().unwrap_or_else(PoisonError::into_inner);
 
-            if let Some(output) = output {
-                self.outputs.insert(action.id, output);
+        match progress.error {
+            Some(err) => Err(err),
+            None => Ok(())
+        }
+    }
+
+    fn work(&self, known: &HashSet<Id>, progress: &Mutex<Progress>, changed: &Condvar) {
+        let mut guard = progress.lock().unwrap_or_else(PoisonError::into_inner);
+
+        loop {
+            if guard.error.is_some() || guard.pending.is_empty() {
+                break;
+            }
+
+            let ready = guard.pending.iter().position(|index| {
+                self.ctx[*index]
+                    .deps()
+                    .iter()
+                    .all(|dep| !known.contains(&dep.id()) || guard.done.contains(&dep.id()))
+            });
+
+            let Some(position) = ready else {
+                if guard.running == 0 {
+                    // Nothing is running, so nothing will
+                    // ever become ready.
+                    guard.error = Some(self.cycle_error(&guard.pending));
+                    changed.notify_all();
+                    break;
+                }
+
+                guard = changed.wait(guard).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+
+            let action = &self.ctx[guard.pending.remove(position)];
+            guard.running += 1;
+            drop(guard);
+
+            let running = Running { progress, changed };
+            let result = self.perform_node(action);
+            drop(running);
+
+            guard = progress.lock().unwrap_or_else(PoisonError::into_inner);
+            guard.running -= 1;
+
+            match result {
+                Ok(output) => {
+                    if let Some(output) = output {
+                        self.set_output(action.id, output);
+                    }
+
+                    guard.done.insert(action.id);
+                },
+                Err(err) => {
+                    guard.error.get_or_insert(err);
+                }
             }
+
+            changed.notify_all();
+        }
+    }
+
+    /// Probe and run a single node, reporting its
+    /// progress to subscribers.
+    fn perform_node(&self, action: &Node) -> Result<Option<Output>, Error> {
+        let display_name = action.display_name();
+
+        let probe = action.probe(self)
+            .map_err(|err| err.attribute(action.id, display_name.clone()))?;
+
+        if !probe.needs_run {
+            self.emit(&Event::ActionSkipped {
+                id: action.id,
+                name: display_name,
+                reason: SkipReason::UpToDate
+            });
+
+            return Ok(None)
         }
 
-        Ok(())
+        self.emit(&Event::ActionStarted {
+            id: action.id,
+            name: display_name.clone()
+        });
+
+        let started = Instant::now();
+        let output = action.run(self, Operation::Perform)
+            .map_err(|err| err.attribute(action.id, display_name.clone()));
+
+        match &output {
+            Ok(_) => self.emit(&Event::ActionFinished {
+                id: action.id,
+                name: display_name,
+                elapsed: started.elapsed()
+            }),
+            Err(err) => self.emit(&Event::ActionFailed {
+                id: action.id,
+                name: display_name,
+                elapsed: started.elapsed(),
+                error: err.clone()
+            })
+        }
+
+        output
     }
 
     /// Reverse the workflow.
     /// 
-    /// # Errors
+    /// Actions are rolled back in the reverse of the
+    /// order they ran in, so an action is rolled
+    /// back before its dependencies.
     /// 
-    /// All action errors are handled internally. This
-    /// function will return an error if there is an
-    /// internal error with the runtime itself.
-    /// 
-    /// # Panics
+    /// # Errors
     /// 
-    /// This function uses unwrap, but panics are impossible.
-    /// If a panic occurs, please report it as a bug.
-    pub fn rollback(mut self) -> Result<(), Error> {
-        let actions = &self.ctx;
-        let mut dependencies: HashMap<Id, Vec<Id>> = HashMap::new();
-
-        for action in actions {
+    /// This function will return an error if any of
+    /// the actions cannot be rolled back or fail, or
+    /// if there is an internal error with the runtime
+    /// itself.
+    pub fn rollback(self) -> Result<(), Error> {
+        for action in &self.ctx {
             if !action.probe(&self)?.can_rollback {
-                return Err(Error::OperationNotSupported);
+                return Err(ErrorKind::OperationNotSupported.into());
             }
+        }
 
-            dependencies.insert(action.id, Vec::new());
+        for index in self.order()?.into_iter().rev() {
+            let action = &self.ctx[index];
 
-            action.deps()
-                .iter()
-                .map(|dep| dep.id())
-                .for_each(|id| {
-                    let deps = dependencies.entry(id).or_insert(Vec::new());
-                    deps.push(action.id);
-                });
+            let output = action.run(&self, Operation::Rollback)
+                .map_err(|err| err.attribute(action.id, action.display_name()))?;
+
+            if let Some(output) = output {
+                self.set_output(action.id, output);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sort the actions so that every action comes
+    /// after its dependencies.
+    fn order(&self) -> Result<Vec<usize>, Error> {
+        let known = self.ctx.iter().map(|action| action.id).collect::<HashSet<_>>();
+        let mut pending = (0..self.ctx.len()).collect::<Vec<_>>();
+        let mut done = HashSet::new();
+        let mut order = Vec::new();
+
+        while !pending.is_empty() {
+            let Some(position) = pending.iter().position(|index| {
+                self.ctx[*index]
+                    .deps()
+                    .iter()
+                    .all(|dep| !known.contains(&dep.id()) || done.contains(&dep.id()))
+            }) else {
+                return Err(self.cycle_error(&pending))
+            };
+
+            let index = pending.remove(position);
+            done.insert(self.ctx[index].id);
+            order.push(index);
         }
 
-        let mut order = dependencies
+        Ok(order)
+    }
+
+    fn cycle_error(&self, pending: &[usize]) -> Error {
+        let names = pending
             .iter()
-            .map(|(id, deps)| (*id, deps.len()))
-            .collect::<Vec<_>>();
+            .map(|index| self.ctx[*index].display_name())
+            .collect::<Vec<_>>()
+            .join(", ");
 
-        order.sort_by(|a, b| a.1.cmp(&b.1));
-        let order = order.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        Error::new(ErrorKind::DependencyCycle, format!("Dependency cycle between: {names}"))
+    }
 
-        for id in order {
-            let action = actions
-                .iter()
-                .find(|action| action.id == *id)
-                .unwrap();
+    /// Subscribe to the events of this runtime.
+    /// 
+    /// Every event emitted after this call is sent
+    /// to the returned receiver. The events are the
+    /// same as the ones sent by the async runtime,
+    /// so they can be collected into the same
+    /// reports.
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
 
-            let output = action.run(&self, Operation::Rollback)?;
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
 
-            if let Some(output) = output {
-                self.outputs.insert(action.id, output);
-            }
-        }
+        receiver
+    }
 
-        Ok(())
+    fn emit(&self, event: &Event) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn set_output(&self, id: Id, output: Output) {
+        self.outputs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, output);
     }
 
     /// Get the output of an action.
+    /// 
+    /// Outputs are shared between worker threads,
+    /// so this returns a copy of the output.
     #[must_use]
-    pub fn get_output(&self, obj: &Node) -> Option<&Output> {
-        self.outputs.get(&obj.id)
+    pub fn get_output(&self, obj: &Node) -> Option<Output> {
+        self.outputs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&obj.id)
+            .cloned()
+    }
+
+    /// Resolve an input to its value.
+    /// 
+    /// Static inputs are cloned. Dynamic inputs are
+    /// read from the output of their node, which
+    /// should be a dependency of the action that
+    /// resolves the input.
+    /// 
+    /// # Errors
+    /// 
+    /// This function returns an error if the node
+    /// has not returned a value, or if the value is
+    /// not of the requested type.
+    pub fn resolve<T>(&self, input: &Input<T>) -> Result<T, Error>
+    where
+        T: Clone + TryFrom<Output, Error = Error>
+    {
+        match input {
+            Input::Static(value) => Ok(value.clone()),
+            Input::Dynamic(node) => self.get_output(node)
+                .ok_or(Error::from(ErrorKind::NoActionReturn))?
+                .try_into()
+        }
     }
 
     /// Get the state object of a given type.
@@ -132,6 +379,35 @@ impl<'run> Runtime<'run> {
             .get(&TypeId::of::<T>())
             .and_then(|state| state.downcast_ref::<T>())
     }
+
+    #[cfg(feature = "async")]
+    /// Create a runtime with no actions, for running
+    /// a single action outside of a workflow.
+    pub(crate) fn detached(
+        outputs: HashMap<Id, Output>,
+        state: HashMap<TypeId, Arc<dyn Any + Send + Sync>>
+    ) -> Self {
+        Self {
+            ctx: Vec::new(),
+            outputs: Mutex::new(outputs),
+            state,
+            subscribers: Mutex::new(Vec::new()),
+            workers: 1
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn outputs_snapshot(&self) -> HashMap<Id, Output> {
+        self.outputs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn state_map(&self) -> &HashMap<TypeId, Arc<dyn Any + Send + Sync>> {
+        &self.state
+    }
 }
 
 /// A builder for a runtime.
@@ -140,24 +416,26 @@ impl<'run> Runtime<'run> {
 /// you have added all of your actions, you can
 /// call [`build`] to create the runtime.\
 #[allow(clippy::module_name_repetitions)]
-pub struct RuntimeBuilder<'build> {
-    ctx: Vec<Node<'build>>,
-    state: HashMap<TypeId, Box<dyn Any>>
+pub struct RuntimeBuilder {
+    ctx: Vec<Node>,
+    state: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    workers: usize
 }
 
-impl<'build> RuntimeBuilder<'build> {
+impl RuntimeBuilder {
     /// Create a new builder.
     #[must_use]
     pub fn new() -> Self {
         Self {
             ctx: Vec::new(),
-            state: HashMap::new()
+            state: HashMap::new(),
+            workers: 1
         }
     }
 
     /// Add an action to the runtime.
     #[must_use]
-    pub fn add_action(mut self, action: Node<'build>) -> Self {
+    pub fn add_action(mut self, action: Node) -> Self {
         action.load_state(&mut self);
         self.ctx.push(action);
         self
@@ -165,7 +443,7 @@ impl<'build> RuntimeBuilder<'build> {
 
     /// Add a scope to the runtime.
     #[must_use]
-    pub fn add_scope(mut self, scope: Scope<'build>) -> Self {
+    pub fn add_scope(mut self, scope: Scope) -> Self {
         for action in scope.actions_owned() {
             self = self.add_action(action);
         }
@@ -173,24 +451,48 @@ impl<'build> RuntimeBuilder<'build> {
         self
     }
 
+    /// Set the number of worker threads.
+    /// 
+    /// With more than one worker, independent
+    /// actions run in parallel on scoped threads.
+    /// The default is one, which runs every action
+    /// on the calling thread.
+    #[must_use]
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
     /// Build the runtime.
     #[must_use]
-    pub fn build(self) -> Runtime<'build> {
+    pub fn build(self) -> Runtime {
         Runtime {
             ctx: self.ctx,
-            outputs: HashMap::new(),
-            state: self.state
+            outputs: Mutex::new(HashMap::new()),
+            state: self.state,
+            subscribers: Mutex::new(Vec::new()),
+            workers: self.workers
         }
     }
 
     /// Add a state object to the runtime.
-    pub fn add_state<T: Any>(&mut self, state: T) -> &mut Self {
-        self.state.insert(TypeId::of::<T>(), Box::new(state));
+    pub fn add_state<T: Any + Send + Sync>(&mut self, state: T) -> &mut Self {
+        self.state.insert(TypeId::of::<T>(), Arc::new(state));
         self
     }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn state_map(&self) -> &HashMap<TypeId, Arc<dyn Any + Send + Sync>> {
+        &self.state
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn insert_raw_state(&mut self, id: TypeId, state: Arc<dyn Any + Send + Sync>) {
+        self.state.insert(id, state);
+    }
 }
 
-impl Default for RuntimeBuilder<'_> {
+impl Default for RuntimeBuilder {
     fn default() -> Self {
         Self::new()
     }
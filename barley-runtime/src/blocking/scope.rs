@@ -7,12 +7,12 @@ use super::action::Node;
 /// execution context, but it is useful
 /// for simplifying the creation of
 /// runtimes with many actions.
-#[derive(Default)]
-pub struct Scope<'scope> {
-    actions: Vec<Node<'scope>>
+#[derive(Default, Clone)]
+pub struct Scope {
+    actions: Vec<Node>
 }
 
-impl<'scope> Scope<'scope> {
+impl Scope {
     /// Create a new scope.
     #[must_use]
     pub fn new() -> Self {
@@ -26,9 +26,10 @@ impl<'scope> Scope<'scope> {
     /// The action object will be returned
     /// so that it can be used to add
     /// dependencies.
-    pub fn add_action<A: Into<Node<'scope>>>(&mut self, action: A) {
+    pub fn add_action<A: Into<Node>>(&mut self, action: A) -> Node {
         let action = action.into();
-        self.actions.push(action);
+        self.actions.push(action.clone());
+        action
     }
 
     /// List the actions in the scope.
@@ -37,7 +38,7 @@ impl<'scope> Scope<'scope> {
         &self.actions
     }
 
-    pub(crate) fn actions_owned(self) -> Vec<Node<'scope>> {
+    pub(crate) fn actions_owned(self) -> Vec<Node> {
         self.actions
     }
 }
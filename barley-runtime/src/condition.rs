@@ -0,0 +1,230 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use crate::{Runtime, Node, Output, Error};
+
+
+/// A check that decides whether a node runs.
+///
+/// Conditions are evaluated right before a node
+/// would be probed. If the condition is not met,
+/// the node is skipped, and its dependents still
+/// run.
+///
+/// Any closure taking a [`Runtime`] reference and
+/// returning a `bool` is also a condition, which
+/// is useful for checking variables and state.
+///
+/// [`Runtime`]: ../struct.Runtime.html
+#[async_trait]
+pub trait Condition: Send + Sync {
+    /// Evaluate the condition.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the
+    /// condition could not be evaluated.
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error>;
+
+    /// Get the nodes that the condition reads
+    /// from.
+    ///
+    /// These nodes are added as dependencies of
+    /// any node that uses this condition.
+    fn dependencies(&self) -> Vec<Node> {
+        Vec::new()
+    }
+}
+
+#[async_trait]
+impl<F> Condition for F
+where
+    F: Fn(&Runtime) -> bool + Send + Sync
+{
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        Ok(self(&runtime))
+    }
+}
+
+#[async_trait]
+impl Condition for Arc<dyn Condition> {
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        self.as_ref().evaluate(runtime).await
+    }
+
+    fn dependencies(&self) -> Vec<Node> {
+        self.as_ref().dependencies()
+    }
+}
+
+/// Checks the output of a node.
+///
+/// The condition is met when the node returned
+/// the expected output. A node that returned
+/// nothing never meets the condition.
+pub struct IfOutput {
+    node: Node,
+    expected: Output
+}
+
+impl IfOutput {
+    /// Check that a node returned a given value.
+    pub fn equals<O: Into<Output>>(node: Node, expected: O) -> Self {
+        Self {
+            node,
+            expected: expected.into()
+        }
+    }
+
+    /// Check that a node returned `true`.
+    #[must_use]
+    pub fn is_true(node: Node) -> Self {
+        Self::equals(node, true)
+    }
+}
+
+#[async_trait]
+impl Condition for IfOutput {
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        Ok(runtime.get_output(self.node.clone()).await.as_ref() == Some(&self.expected))
+    }
+
+    fn dependencies(&self) -> Vec<Node> {
+        vec![self.node.clone()]
+    }
+}
+
+/// Checks a runtime variable.
+///
+/// The condition is met when the variable is set,
+/// has the same type as the expected value, and
+/// is equal to it.
+pub struct IfVariable<T> {
+    name: String,
+    expected: T
+}
+
+impl<T> IfVariable<T> {
+    /// Check that a variable has a given value.
+    pub fn equals<S: Into<String>>(name: S, expected: T) -> Self {
+        Self {
+            name: name.into(),
+            expected
+        }
+    }
+}
+
+impl IfVariable<bool> {
+    /// Check that a variable is `true`.
+    pub fn is_true<S: Into<String>>(name: S) -> Self {
+        Self::equals(name, true)
+    }
+}
+
+#[async_trait]
+impl<T> Condition for IfVariable<T>
+where
+    T: PartialEq + Send + Sync + 'static
+{
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        Ok(runtime
+            .get_variable::<T>(&self.name)
+            .is_some_and(|value| *value == self.expected))
+    }
+}
+
+/// Inverts a condition.
+pub struct Not(Arc<dyn Condition>);
+
+impl Not {
+    /// Invert a condition.
+    pub fn new<C: Condition + 'static>(condition: C) -> Self {
+        Self(Arc::new(condition))
+    }
+}
+
+#[async_trait]
+impl Condition for Not {
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        Ok(!self.0.evaluate(runtime).await?)
+    }
+
+    fn dependencies(&self) -> Vec<Node> {
+        self.0.dependencies()
+    }
+}
+
+/// Met when every inner condition is met.
+#[derive(Default)]
+pub struct All(Vec<Arc<dyn Condition>>);
+
+impl All {
+    /// Create an empty set of conditions.
+    ///
+    /// An empty set is always met.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add a condition to the set.
+    #[must_use]
+    pub fn and<C: Condition + 'static>(mut self, condition: C) -> Self {
+        self.0.push(Arc::new(condition));
+        self
+    }
+}
+
+#[async_trait]
+impl Condition for All {
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        for condition in &self.0 {
+            if !condition.evaluate(runtime.clone()).await? {
+                return Ok(false)
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn dependencies(&self) -> Vec<Node> {
+        self.0.iter().flat_map(Condition::dependencies).collect()
+    }
+}
+
+/// Met when any inner condition is met.
+#[derive(Default)]
+pub struct Any(Vec<Arc<dyn Condition>>);
+
+impl Any {
+    /// Create an empty set of conditions.
+    ///
+    /// An empty set is never met.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add a condition to the set.
+    #[must_use]
+    pub fn or<C: Condition + 'static>(mut self, condition: C) -> Self {
+        self.0.push(Arc::new(condition));
+        self
+    }
+}
+
+#[async_trait]
+impl Condition for Any {
+    async fn evaluate(&self, runtime: Runtime) -> Result<bool, Error> {
+        for condition in &self.0 {
+            if condition.evaluate(runtime.clone()).await? {
+                return Ok(true)
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn dependencies(&self) -> Vec<Node> {
+        self.0.iter().flat_map(Condition::dependencies).collect()
+    }
+}
@@ -1,29 +1,212 @@
-use thiserror::Error;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
 
+use crate::Id;
 
-/// Any error that can occur during an action.
-#[derive(Debug, Error, Clone)]
+
+/// The kind of an [`Error`].
+///
+/// Kinds are stable, and can be matched on to
+/// handle specific errors. New kinds may be added
+/// in the future.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
-pub enum Error {
+pub enum ErrorKind {
     /// An error occured internally in the action.
-    #[error("{0}")]
-    ActionFailed(String, String),
+    ActionFailed,
+    /// An I/O operation failed.
+    Io,
+    /// A process exited unsuccessfully.
+    CommandFailed,
     /// Action output conversion failed.
-    #[error("Could not convert ActionOutput to {0}")]
-    OutputConversionFailed(String),
+    OutputConversionFailed,
     /// An internal error occured, and should be reported.
-    #[error("An internal error occured, please report this error code: {0}")]
-    InternalError(&'static str),
+    Internal,
     /// An action which should have returned a value did not.
-    #[error("Dependency did not return a value")]
     NoActionReturn,
     /// The operation is not supported by the action.
-    #[error("Operation not supported")]
     OperationNotSupported,
     /// Required state was not loaded.
-    #[error("Required state was not loaded")]
     StateNotLoaded,
     /// A dependency returned the wrong type.
-    #[error("Dependency returned the wrong type")]
-    WrongOutputType
-}
\ No newline at end of file
+    WrongOutputType,
+    /// Actions depend on each other in a cycle.
+    DependencyCycle,
+    /// An action took longer than its timeout.
+    TimedOut
+}
+
+impl ErrorKind {
+    fn default_message(self) -> &'static str {
+        match self {
+            Self::ActionFailed => "Action failed",
+            Self::Io => "I/O error",
+            Self::CommandFailed => "Command failed",
+            Self::OutputConversionFailed => "Could not convert Output",
+            Self::Internal => "An internal error occured, please report this error",
+            Self::NoActionReturn => "Dependency did not return a value",
+            Self::OperationNotSupported => "Operation not supported",
+            Self::StateNotLoaded => "Required state was not loaded",
+            Self::WrongOutputType => "Dependency returned the wrong type",
+            Self::DependencyCycle => "Actions depend on each other in a cycle",
+            Self::TimedOut => "Action timed out"
+        }
+    }
+}
+
+/// Any error that can occur during an action.
+///
+/// Every error has an [`ErrorKind`] and a short
+/// message. It can also carry a longer detail
+/// message, the exit code of a failed process,
+/// and the error that caused it. When an action
+/// fails, the runtime records which node the
+/// error came from.
+///
+/// Errors are cheap to clone, so the source is
+/// kept behind an [`Arc`].
+///
+/// [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+#[derive(Debug, Clone)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+    detail: Option<String>,
+    exit_code: Option<i32>,
+    node: Option<(Id, String)>,
+    source: Option<Arc<dyn StdError + Send + Sync>>
+}
+
+impl Error {
+    /// Create a new error with a kind and message.
+    pub fn new<S: Into<String>>(kind: ErrorKind, message: S) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            detail: None,
+            exit_code: None,
+            node: None,
+            source: None
+        }
+    }
+
+    /// Create an error for a failed action.
+    pub fn action_failed<S: Into<String>>(message: S) -> Self {
+        Self::new(ErrorKind::ActionFailed, message)
+    }
+
+    /// Create an internal runtime error.
+    ///
+    /// The code identifies where the error came
+    /// from, and should be included in bug reports.
+    #[must_use]
+    pub fn internal(code: &'static str) -> Self {
+        Self::new(
+            ErrorKind::Internal,
+            format!("An internal error occured, please report this error code: {code}")
+        )
+    }
+
+    /// Add a longer description of the error.
+    #[must_use]
+    pub fn with_detail<S: Into<String>>(mut self, detail: S) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Add the exit code of a failed process.
+    #[must_use]
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    /// Add the error that caused this one.
+    #[must_use]
+    pub fn with_source<E>(mut self, source: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static
+    {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    /// Record the node that failed, unless the
+    /// error already belongs to one.
+    #[must_use]
+    pub(crate) fn attribute(mut self, id: Id, name: String) -> Self {
+        if self.node.is_none() {
+            self.node = Some((id, name));
+        }
+
+        self
+    }
+
+    /// Get the kind of the error.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Get the short message of the error.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the longer description of the error,
+    /// if there is one.
+    #[must_use]
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// Get the exit code of the failed process,
+    /// if there is one.
+    #[must_use]
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Get the ID of the node that failed.
+    #[must_use]
+    pub fn node_id(&self) -> Option<Id> {
+        self.node.as_ref().map(|(id, _)| *id)
+    }
+
+    /// Get the display name of the node that failed.
+    #[must_use]
+    pub fn node_name(&self) -> Option<&str> {
+        self.node.as_ref().map(|(_, name)| name.as_str())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.node {
+            Some((_, name)) => write!(f, "{name}: {}", self.message),
+            None => write!(f, "{}", self.message)
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn StdError + 'static))
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind, kind.default_message())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(ErrorKind::Io, error.to_string()).with_source(error)
+    }
+}
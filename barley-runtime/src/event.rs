@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use crate::{Error, Id};
+
+
+/// The reason an action was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// The action's probe reported that it did
+    /// not need to run.
+    UpToDate,
+    /// The action's condition was not met.
+    ConditionNotMet,
+    /// An action that it depends on failed.
+    DependencyFailed,
+    /// The action has none of the tags that the
+    /// runtime is limited to.
+    NotTagged
+}
+
+impl SkipReason {
+    /// Get a short description of the reason.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UpToDate => "up to date",
+            Self::ConditionNotMet => "condition not met",
+            Self::DependencyFailed => "dependency failed",
+            Self::NotTagged => "not tagged"
+        }
+    }
+}
+
+/// An event emitted while a workflow is running.
+///
+/// Events are sent to every subscriber of a
+/// runtime, in the order that they happened.
+/// They can be used to display progress, write
+/// logs, or build reports about a run.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    /// The workflow has started.
+    RunStarted {
+        /// The ID and display name of every action
+        /// in the workflow.
+        actions: Vec<(Id, String)>
+    },
+    /// An action was added while the workflow was
+    /// running.
+    ActionAdded {
+        /// The ID of the action.
+        id: Id,
+        /// The display name of the action.
+        name: String,
+        /// The ID of the action that added it, if
+        /// it was added by a running action.
+        parent: Option<Id>
+    },
+    /// An action has started running.
+    ActionStarted {
+        /// The ID of the action.
+        id: Id,
+        /// The display name of the action.
+        name: String
+    },
+    /// An action did not run.
+    ActionSkipped {
+        /// The ID of the action.
+        id: Id,
+        /// The display name of the action.
+        name: String,
+        /// Why the action did not run.
+        reason: SkipReason
+    },
+    /// An action finished successfully.
+    ActionFinished {
+        /// The ID of the action.
+        id: Id,
+        /// The display name of the action.
+        name: String,
+        /// How long the action took to run.
+        elapsed: Duration
+    },
+    /// An action failed.
+    ActionFailed {
+        /// The ID of the action.
+        id: Id,
+        /// The display name of the action.
+        name: String,
+        /// How long the action ran before failing.
+        elapsed: Duration,
+        /// The error returned by the action.
+        error: Error
+    },
+    /// An attempt of an action failed, and the
+    /// action will be run again.
+    ActionRetried {
+        /// The ID of the action.
+        id: Id,
+        /// The display name of the action.
+        name: String,
+        /// The number of the attempt that failed,
+        /// starting at 1.
+        attempt: u32,
+        /// The error returned by the attempt.
+        error: Error
+    },
+    /// A running action produced a line of output.
+    ActionOutput {
+        /// The ID of the action.
+        id: Id,
+        /// The line, without a trailing newline.
+        line: String
+    },
+    /// The workflow has finished.
+    RunFinished {
+        /// Whether every action succeeded.
+        success: bool,
+        /// How long the whole workflow took.
+        elapsed: Duration
+    }
+}
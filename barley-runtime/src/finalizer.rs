@@ -0,0 +1,116 @@
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::{debug, error};
+
+use crate::error::Error;
+
+
+struct Finalizer {
+    name: String,
+    run: Box<dyn FnOnce() -> BoxFuture<'static, Result<(), Error>> + Send>
+}
+
+/// Cleanup work registered by actions, run once
+/// the workflow ends.
+///
+/// Finalizers run in the reverse of the order
+/// they were added, like destructors.
+#[derive(Clone, Default)]
+pub(crate) struct Finalizers {
+    pending: Arc<Mutex<Vec<Finalizer>>>
+}
+
+impl Finalizers {
+    pub(crate) fn push(
+        &self,
+        name: String,
+        run: Box<dyn FnOnce() -> BoxFuture<'static, Result<(), Error>> + Send>
+    ) {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Finalizer { name, run });
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
+    }
+
+    fn take(&self) -> Vec<Finalizer> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Run every finalizer, including those added
+    /// by other finalizers.
+    ///
+    /// Every finalizer runs even if an earlier one
+    /// fails. The first failure is returned.
+    pub(crate) async fn run(&self) -> Result<(), Error> {
+        let mut first_error = None;
+
+        loop {
+            let finalizers = self.take();
+
+            if finalizers.is_empty() {
+                break;
+            }
+
+            for finalizer in finalizers.into_iter().rev() {
+                debug!("Running finalizer: {}", finalizer.name);
+
+                if let Err(err) = (finalizer.run)().await {
+                    error!("Finalizer failed: {}: {}", finalizer.name, err);
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Run the finalizers if the guard is dropped
+    /// before [`run`] is called, such as when the
+    /// workflow is cancelled.
+    ///
+    /// [`run`]: #method.run
+    pub(crate) fn guard(&self) -> Guard {
+        Guard {
+            finalizers: Some(self.clone())
+        }
+    }
+}
+
+pub(crate) struct Guard {
+    finalizers: Option<Finalizers>
+}
+
+impl Guard {
+    /// Run the finalizers now.
+    pub(crate) async fn run(mut self) -> Result<(), Error> {
+        match self.finalizers.take() {
+            Some(finalizers) => finalizers.run().await,
+            None => Ok(())
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let Some(finalizers) = self.finalizers.take() else {
+            return;
+        };
+
+        // Drop can't wait, so the finalizers run in
+        // the background. Without a Tokio runtime to
+        // run them on, they are dropped instead.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                let _ = finalizers.run().await;
+            });
+        }
+    }
+}
@@ -36,27 +36,99 @@ pub mod prelude;
 #[cfg(feature = "blocking")]
 pub mod blocking;
 
+/// Adapters between blocking and async actions.
+/// 
+/// Actions written for one runtime can be used in
+/// the other by converting their nodes with
+/// [`to_async`] or [`to_blocking`], so a single
+/// library of actions can serve both.
+/// 
+/// [`to_async`]: adapter/fn.to_async.html
+/// [`to_blocking`]: adapter/fn.to_blocking.html
+#[cfg(all(feature = "async", feature = "blocking"))]
+pub mod adapter;
+
+/// A live terminal view of a running workflow.
+/// 
+/// This module renders the events of a [`Runtime`]
+/// as a list of actions with their state, elapsed
+/// time, and latest output. When stdout is not a
+/// terminal, it prints one line per event instead.
+/// 
+/// [`Runtime`]: struct.Runtime.html
+#[cfg(feature = "progress")]
+pub mod progress;
+
+/// Reports of completed runs.
+/// 
+/// This module turns the events of a run into a
+/// [`Report`], which can be exported as `JUnit` XML
+/// or JSON for CI systems and other tools.
+/// 
+/// [`Report`]: report/struct.Report.html
+#[cfg(feature = "report")]
+pub mod report;
+
+/// Helpers for testing actions.
+/// 
+/// This module contains a [`MockRuntime`] for
+/// running a single action against preset outputs,
+/// state and variables, a scriptable [`FakeAction`],
+/// and a [`Recorder`] for checking the order in
+/// which a runtime ran actions.
+/// 
+/// [`MockRuntime`]: testing/struct.MockRuntime.html
+/// [`FakeAction`]: testing/struct.FakeAction.html
+/// [`Recorder`]: testing/struct.Recorder.html
+#[cfg(feature = "testing")]
+pub mod testing;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "async")] {
         mod context;
         mod runtime;
+        mod scheduler;
+        mod finalizer;
         mod scope;
         mod action;
         mod input;
+        mod matrix;
+        mod settings;
+        mod workflow;
+        mod module;
+
+        /// Conditions for running actions.
+        /// 
+        /// A [`Condition`] can be attached to a node
+        /// with [`Node::run_if`]. This module contains
+        /// the built-in conditions, which check outputs
+        /// and variables, and combine other conditions.
+        /// 
+        /// [`Condition`]: condition/trait.Condition.html
+        /// [`Node::run_if`]: struct.Node.html#method.run_if
+        pub mod condition;
 
         pub use runtime::{Runtime, RuntimeBuilder};
         pub use action::{Action, Node};
         pub use input::Input;
         pub use scope::Scope;
+        pub use matrix::Matrix;
+        pub use settings::{Settings, FailurePolicy};
+        pub use workflow::SubWorkflow;
+        pub use module::{Module, Instance, Exports};
+        pub use condition::Condition;
     }
 }
 
 mod error;
-pub use error::Error;
+pub use error::{Error, ErrorKind};
 
 mod output;
 pub use output::Output;
 
+mod event;
+pub use event::{Event, SkipReason};
+
 /// A unique identifier for an action.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Id(Uuid);
@@ -0,0 +1,281 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, PoisonError};
+use crate::{
+    Action, Runtime, Node, Output,
+    Error, ErrorKind, Probe, Operation,
+    Scope
+};
+
+
+type Factory = Arc<dyn Fn(&[Output]) -> Node + Send + Sync>;
+
+enum Axis {
+    Static(Vec<Output>),
+    Dynamic(Node)
+}
+
+/// Builds one action for every combination of
+/// a set of parameters.
+///
+/// Each axis is a list of values, either given
+/// up front or read from the [`Output::List`] of
+/// another node. The factory is called once for
+/// every combination, and the resulting action is
+/// named after its parameters, like
+/// `Install [curl, host-a]`.
+///
+/// A matrix is created with [`Scope::matrix`].
+///
+/// [`Output::List`]: enum.Output.html#variant.List
+/// [`Scope::matrix`]: struct.Scope.html#method.matrix
+pub struct Matrix<'scope> {
+    scope: &'scope mut Scope,
+    factory: Factory,
+    axes: Vec<Axis>,
+    name: String,
+    aggregate: bool
+}
+
+impl<'scope> Matrix<'scope> {
+    pub(crate) fn new(scope: &'scope mut Scope, factory: Factory) -> Self {
+        Self {
+            scope,
+            factory,
+            axes: Vec::new(),
+            name: "Matrix".to_string(),
+            aggregate: false
+        }
+    }
+
+    /// Add an axis with a fixed list of values.
+    #[must_use]
+    pub fn axis<I, O>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = O>,
+        O: Into<Output>
+    {
+        self.axes.push(Axis::Static(values.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Add an axis read from the output of a node.
+    ///
+    /// The node must return an [`Output::List`].
+    /// The actions of the matrix are then added
+    /// to the workflow once the node has run.
+    ///
+    /// [`Output::List`]: enum.Output.html#variant.List
+    #[must_use]
+    pub fn axis_from(mut self, node: Node) -> Self {
+        self.axes.push(Axis::Dynamic(node));
+        self
+    }
+
+    /// Set the display name of the node returned
+    /// by [`build`].
+    ///
+    /// [`build`]: #method.build
+    #[must_use]
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Collect the outputs of every action into an
+    /// [`Output::List`].
+    ///
+    /// The list is returned by the node that
+    /// [`build`] returns, in the order of the
+    /// combinations. Actions that returned nothing
+    /// are left out.
+    ///
+    /// [`Output::List`]: enum.Output.html#variant.List
+    /// [`build`]: #method.build
+    #[must_use]
+    pub fn aggregate(mut self) -> Self {
+        self.aggregate = true;
+        self
+    }
+
+    /// Add the actions of the matrix to the scope.
+    ///
+    /// The returned node runs after every action
+    /// of the matrix, so it can be used as a
+    /// dependency for the whole matrix.
+    #[allow(clippy::must_use_candidate)]
+    pub fn build(self) -> Node {
+        let dynamic = self.axes
+            .iter()
+            .filter_map(|axis| match axis {
+                Axis::Dynamic(node) => Some(node.clone()),
+                Axis::Static(_) => None
+            })
+            .collect::<Vec<_>>();
+
+        let mut deps = Vec::new();
+        let children;
+
+        if dynamic.is_empty() {
+            let axes = self.axes
+                .into_iter()
+                .filter_map(|axis| match axis {
+                    Axis::Static(values) => Some(values),
+                    Axis::Dynamic(_) => None
+                })
+                .collect::<Vec<_>>();
+
+            for params in combinations(&axes) {
+                deps.push(self.scope.add_action(create(&self.factory, &params)));
+            }
+
+            children = Arc::new(Mutex::new(deps.clone()));
+        } else {
+            children = Arc::new(Mutex::new(Vec::new()));
+
+            let mut expander: Node = Expander {
+                name: self.name.clone(),
+                factory: self.factory,
+                axes: self.axes,
+                children: children.clone()
+            }.into();
+
+            for node in dynamic {
+                expander.requires(node);
+            }
+
+            deps.push(self.scope.add_action(expander));
+        }
+
+        let mut collector: Node = Collector {
+            name: self.name,
+            children,
+            aggregate: self.aggregate
+        }.into();
+
+        for dep in deps {
+            collector.requires(dep);
+        }
+
+        self.scope.add_action(collector)
+    }
+}
+
+fn create(factory: &Factory, params: &[Output]) -> Node {
+    let mut node = factory(params);
+    let params = params
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    node.set_name(format!("{} [{params}]", node.display_name()));
+    node
+}
+
+fn combinations(axes: &[Vec<Output>]) -> Vec<Vec<Output>> {
+    if axes.is_empty() {
+        return Vec::new()
+    }
+
+    axes.iter().fold(vec![Vec::new()], |combinations, axis| {
+        combinations
+            .iter()
+            .flat_map(|params| axis.iter().map(move |value| {
+                let mut params = params.clone();
+                params.push(value.clone());
+                params
+            }))
+            .collect()
+    })
+}
+
+struct Expander {
+    name: String,
+    factory: Factory,
+    axes: Vec<Axis>,
+    children: Arc<Mutex<Vec<Node>>>
+}
+
+#[async_trait]
+impl Action for Expander {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Ok(None)
+        }
+
+        // A retry starts over, so children from an
+        // earlier attempt must not be collected.
+        self.children.lock().unwrap_or_else(PoisonError::into_inner).clear();
+
+        let mut axes = Vec::new();
+
+        for axis in &self.axes {
+            axes.push(match axis {
+                Axis::Static(values) => values.clone(),
+                Axis::Dynamic(node) => runtime.get_output(node.clone()).await
+                    .ok_or(Error::from(ErrorKind::NoActionReturn))?
+                    .try_into()
+                    .map_err(|_| Error::new(
+                        ErrorKind::WrongOutputType,
+                        format!("{} did not return a list", node.display_name())
+                    ))?
+            });
+        }
+
+        for params in combinations(&axes) {
+            let node = create(&self.factory, &params);
+            self.children.lock().unwrap_or_else(PoisonError::into_inner).push(node.clone());
+            runtime.add_action(node);
+        }
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Expand {}", self.name)
+    }
+}
+
+struct Collector {
+    name: String,
+    children: Arc<Mutex<Vec<Node>>>,
+    aggregate: bool
+}
+
+#[async_trait]
+impl Action for Collector {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) || !self.aggregate {
+            return Ok(None)
+        }
+
+        let children = self.children.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        let mut outputs = Vec::new();
+
+        for child in children {
+            if let Some(output) = runtime.get_output(child).await {
+                outputs.push(output);
+            }
+        }
+
+        Ok(Some(Output::List(outputs)))
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use crate::{Node, Scope};
+
+
+/// A reusable group of actions.
+///
+/// A module turns a set of typed parameters into
+/// a [`Scope`], and names the nodes in that scope
+/// that callers may depend on or read outputs
+/// from. The same module can be added several
+/// times with different parameters.
+///
+/// # Example
+///
+/// ```no_run
+/// use barley_runtime::prelude::*;
+/// use barley_runtime::{Instance, Module};
+///
+/// struct ServiceParams {
+///     name: String
+/// }
+///
+/// struct Service;
+///
+/// impl Module for Service {
+///     type Params = ServiceParams;
+///
+///     fn build(&self, params: ServiceParams) -> Instance {
+///         let scope = Scope::named(params.name);
+///         // Add the service's actions here.
+///
+///         Instance::new(scope)
+///     }
+/// }
+///
+/// let mut scope = Scope::new();
+/// let web = scope.add_module(&Service, ServiceParams { name: "web".to_string() });
+/// let worker = scope.add_module(&Service, ServiceParams { name: "worker".to_string() });
+/// ```
+///
+/// [`Scope`]: struct.Scope.html
+pub trait Module {
+    /// The parameters of the module.
+    type Params;
+
+    /// Build one instance of the module.
+    fn build(&self, params: Self::Params) -> Instance;
+}
+
+/// A module built with a set of parameters.
+pub struct Instance {
+    scope: Scope,
+    exports: BTreeMap<String, Node>
+}
+
+impl Instance {
+    /// Create an instance from a scope, with no
+    /// exported nodes.
+    #[must_use]
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            exports: BTreeMap::new()
+        }
+    }
+
+    /// Export a node under a name.
+    #[must_use]
+    pub fn export<S: Into<String>>(mut self, name: S, node: Node) -> Self {
+        self.exports.insert(name.into(), node);
+        self
+    }
+
+    /// Get the scope of the instance.
+    #[must_use]
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    /// Split the instance into its scope and its
+    /// exported nodes.
+    #[must_use]
+    pub fn into_parts(self) -> (Scope, Exports) {
+        let exports = Exports {
+            node: self.scope.as_node(),
+            exports: self.exports
+        };
+
+        (self.scope, exports)
+    }
+}
+
+/// The exported nodes of a module that has been
+/// added to a scope.
+#[derive(Clone)]
+pub struct Exports {
+    node: Node,
+    exports: BTreeMap<String, Node>
+}
+
+impl Exports {
+    /// Get an exported node by name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Node> {
+        self.exports.get(name).cloned()
+    }
+
+    /// List the names of the exported nodes.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.exports.keys().map(String::as_str)
+    }
+
+    /// Get a node that stands for the whole
+    /// module. See [`Scope::as_node`].
+    ///
+    /// [`Scope::as_node`]: struct.Scope.html#method.as_node
+    #[must_use]
+    pub fn as_node(&self) -> Node {
+        self.node.clone()
+    }
+}
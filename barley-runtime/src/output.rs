@@ -1,4 +1,6 @@
-use crate::Error;
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::{Error, ErrorKind};
 
 
 /// The output of an action.
@@ -8,7 +10,7 @@ use crate::Error;
 /// other actions depending on said value.
 /// 
 /// [`Action`]: trait.Action.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Output {
     /// A string.
     String(String),
@@ -17,7 +19,48 @@ pub enum Output {
     /// A floating-point number (f64).
     Float(f64),
     /// A boolean.
-    Boolean(bool)
+    Boolean(bool),
+    /// A list of outputs.
+    List(Vec<Output>),
+    /// Named outputs, sorted by name.
+    Map(BTreeMap<String, Output>)
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(value) => write!(f, "{value}"),
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::Boolean(value) => write!(f, "{value}"),
+            Self::List(values) => {
+                write!(f, "[")?;
+
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{value}")?;
+                }
+
+                write!(f, "]")
+            },
+            Self::Map(values) => {
+                write!(f, "{{")?;
+
+                for (i, (name, value)) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{name}: {value}")?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
 }
 
 impl TryFrom<Output> for String {
@@ -26,7 +69,7 @@ impl TryFrom<Output> for String {
     fn try_from(value: Output) -> Result<Self, Self::Error> {
         match value {
             Output::String(value) => Ok(value),
-            _ => Err(Error::OutputConversionFailed("String".to_string()))
+            _ => Err(Error::new(ErrorKind::OutputConversionFailed, "Could not convert Output to String"))
         }
     }
 }
@@ -37,7 +80,7 @@ impl TryFrom<Output> for i64 {
     fn try_from(value: Output) -> Result<Self, Self::Error> {
         match value {
             Output::Integer(value) => Ok(value),
-            _ => Err(Error::OutputConversionFailed("i64".to_string()))
+            _ => Err(Error::new(ErrorKind::OutputConversionFailed, "Could not convert Output to i64"))
         }
     }
 }
@@ -48,7 +91,7 @@ impl TryFrom<Output> for f64 {
     fn try_from(value: Output) -> Result<Self, Self::Error> {
         match value {
             Output::Float(value) => Ok(value),
-            _ => Err(Error::OutputConversionFailed("f64".to_string()))
+            _ => Err(Error::new(ErrorKind::OutputConversionFailed, "Could not convert Output to f64"))
         }
     }
 }
@@ -59,7 +102,29 @@ impl TryFrom<Output> for bool {
     fn try_from(value: Output) -> Result<Self, Self::Error> {
         match value {
             Output::Boolean(value) => Ok(value),
-            _ => Err(Error::OutputConversionFailed("bool".to_string()))
+            _ => Err(Error::new(ErrorKind::OutputConversionFailed, "Could not convert Output to bool"))
+        }
+    }
+}
+
+impl TryFrom<Output> for Vec<Output> {
+    type Error = Error;
+
+    fn try_from(value: Output) -> Result<Self, Self::Error> {
+        match value {
+            Output::List(value) => Ok(value),
+            _ => Err(Error::new(ErrorKind::OutputConversionFailed, "Could not convert Output to Vec"))
+        }
+    }
+}
+
+impl TryFrom<Output> for BTreeMap<String, Output> {
+    type Error = Error;
+
+    fn try_from(value: Output) -> Result<Self, Self::Error> {
+        match value {
+            Output::Map(value) => Ok(value),
+            _ => Err(Error::new(ErrorKind::OutputConversionFailed, "Could not convert Output to BTreeMap"))
         }
     }
 }
@@ -92,4 +157,16 @@ impl From<&str> for Output {
     fn from(value: &str) -> Self {
         Self::String(value.to_string())
     }
-}
\ No newline at end of file
+}
+
+impl<T: Into<Output>> From<Vec<T>> for Output {
+    fn from(value: Vec<T>) -> Self {
+        Self::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, Output>> for Output {
+    fn from(value: BTreeMap<String, Output>) -> Self {
+        Self::Map(value)
+    }
+}
@@ -4,7 +4,9 @@ pub use async_trait::async_trait;
 pub use crate::{
     Action, Runtime,
     RuntimeBuilder, Probe,
-    Operation, Scope
+    Operation, Scope,
+    ErrorKind, Condition,
+    FailurePolicy, Module
 };
 
 #[cfg(not(feature = "next"))]
@@ -0,0 +1,410 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+use crate::{Event, Id, Runtime, SkipReason};
+
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const TICK: Duration = Duration::from_millis(100);
+const TAIL_LINES: usize = 3;
+
+/// A live progress view for a workflow.
+///
+/// The view subscribes to the runtime's events
+/// when it is spawned, and stops once the run
+/// has finished.
+///
+/// # Example
+///
+/// ```no_run
+/// use barley_runtime::prelude::*;
+/// use barley_runtime::progress::Progress;
+///
+/// # async fn run() -> Result<(), barley_runtime::Error> {
+/// let runtime = RuntimeBuilder::new().build();
+/// let progress = Progress::spawn(&runtime);
+///
+/// let result = runtime.perform().await;
+/// progress.finish().await;
+///
+/// result
+/// # }
+/// ```
+pub struct Progress {
+    handle: JoinHandle<()>
+}
+
+impl Progress {
+    /// Start rendering the events of a runtime.
+    ///
+    /// If stdout is a terminal, the view is redrawn
+    /// in place. Otherwise, each event is printed
+    /// as a plain line.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it is not called
+    /// from within a Tokio runtime.
+    #[must_use]
+    pub fn spawn(runtime: &Runtime) -> Self {
+        let events = runtime.subscribe();
+
+        let handle = if io::stdout().is_terminal() {
+            tokio::spawn(Renderer::new(None).live(events, io::stdout()))
+        } else {
+            tokio::spawn(plain(events, io::stdout()))
+        };
+
+        Self { handle }
+    }
+
+    /// Start rendering the events of a runtime into
+    /// a writer.
+    ///
+    /// With a size, given as columns and rows, the
+    /// view is redrawn in place as if the writer
+    /// were a terminal of that size. Without one,
+    /// each event is written as a plain line.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it is not called
+    /// from within a Tokio runtime.
+    #[must_use]
+    pub fn spawn_to<W>(runtime: &Runtime, out: W, size: Option<(usize, usize)>) -> Self
+    where
+        W: Write + Send + 'static
+    {
+        let events = runtime.subscribe();
+
+        let handle = match size {
+            Some(size) => tokio::spawn(Renderer::new(Some(size)).live(events, out)),
+            None => tokio::spawn(plain(events, out))
+        };
+
+        Self { handle }
+    }
+
+    /// Wait for the view to finish drawing.
+    ///
+    /// This returns once the runtime has reported
+    /// the end of the run.
+    pub async fn finish(self) {
+        let _ = self.handle.await;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Pending,
+    Running,
+    Skipped(SkipReason),
+    Finished,
+    Failed
+}
+
+struct Entry {
+    name: String,
+    state: State,
+    started: Option<Instant>,
+    elapsed: Option<Duration>,
+    tail: VecDeque<String>
+}
+
+impl Entry {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            state: State::Pending,
+            started: None,
+            elapsed: None,
+            tail: VecDeque::new()
+        }
+    }
+}
+
+struct Renderer {
+    entries: Vec<Entry>,
+    index: HashMap<Id, usize>,
+    frame: usize,
+    drawn: usize,
+    size: Option<(usize, usize)>
+}
+
+impl Renderer {
+    /// Create a renderer of a fixed size, or one
+    /// that follows the size of the terminal.
+    fn new(size: Option<(usize, usize)>) -> Self {
+        Self {
+            entries: Vec::new(),
+            index: HashMap::new(),
+            frame: 0,
+            drawn: 0,
+            size
+        }
+    }
+
+    async fn live(mut self, mut events: UnboundedReceiver<Event>, mut out: impl Write) {
+        let mut last_draw = Instant::now();
+
+        loop {
+            match timeout(TICK, events.recv()).await {
+                Ok(Some(event)) => {
+                    let finished = matches!(event, Event::RunFinished { .. });
+                    self.record(event);
+
+                    if finished {
+                        break;
+                    }
+                },
+                Ok(None) => break,
+                Err(_) => {}
+            }
+
+            if last_draw.elapsed() >= TICK {
+                self.frame = self.frame.wrapping_add(1);
+                let _ = self.draw(&mut out);
+                last_draw = Instant::now();
+            }
+        }
+
+        let _ = self.draw(&mut out);
+    }
+
+    fn entry(&mut self, id: Id, name: String) -> &mut Entry {
+        let index = *self.index.entry(id).or_insert_with(|| {
+            self.entries.push(Entry::new(name));
+            self.entries.len() - 1
+        });
+
+        &mut self.entries[index]
+    }
+
+    fn record(&mut self, event: Event) {
+        match event {
+            Event::RunStarted { actions } => {
+                for (id, name) in actions {
+                    self.entry(id, name);
+                }
+            },
+            Event::ActionAdded { id, name, .. } => {
+                self.entry(id, name);
+            },
+            Event::ActionStarted { id, name } => {
+                let entry = self.entry(id, name);
+                entry.state = State::Running;
+                entry.started = Some(Instant::now());
+            },
+            Event::ActionSkipped { id, name, reason } => {
+                self.entry(id, name).state = State::Skipped(reason);
+            },
+            Event::ActionFinished { id, name, elapsed } => {
+                let entry = self.entry(id, name);
+                entry.state = State::Finished;
+                entry.elapsed = Some(elapsed);
+            },
+            Event::ActionFailed { id, name, elapsed, .. } => {
+                let entry = self.entry(id, name);
+                entry.state = State::Failed;
+                entry.elapsed = Some(elapsed);
+            },
+            Event::ActionRetried { id, error, .. } => {
+                if let Some(index) = self.index.get(&id) {
+                    let tail = &mut self.entries[*index].tail;
+
+                    tail.push_back(format!("retrying: {error}"));
+                    if tail.len() > TAIL_LINES {
+                        tail.pop_front();
+                    }
+                }
+            },
+            Event::ActionOutput { id, line } => {
+                if let Some(index) = self.index.get(&id) {
+                    let tail = &mut self.entries[*index].tail;
+
+                    tail.push_back(line);
+                    if tail.len() > TAIL_LINES {
+                        tail.pop_front();
+                    }
+                }
+            },
+            Event::RunFinished { .. } => {}
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.size.unwrap_or_else(terminal_size);
+
+        // The cursor can only move back up to the top
+        // of the screen, so the frame must fit below
+        // it, with a row left for the cursor.
+        let rows = height.saturating_sub(1).max(1);
+
+        let mut lines = self.lines(width, false);
+
+        if lines.len() > rows {
+            lines = self.lines(width, true);
+        }
+
+        if lines.len() > rows {
+            let more = lines.len() - (rows - 1);
+            lines.truncate(rows - 1);
+            lines.push(truncate(&format!("… {more} more lines"), width));
+        }
+
+        let mut frame = String::new();
+
+        if self.drawn > 0 {
+            let _ = write!(frame, "\x1b[{}A", self.drawn);
+        }
+
+        for line in &lines {
+            let _ = writeln!(frame, "\x1b[2K{line}");
+        }
+
+        // Clear anything left over from a longer
+        // previous frame.
+        frame.push_str("\x1b[J");
+
+        self.drawn = lines.len();
+
+        out.write_all(frame.as_bytes())?;
+        out.flush()
+    }
+
+    /// Render the lines of a frame. Finished and
+    /// skipped actions can be folded into one line
+    /// to save space.
+    fn lines(&self, width: usize, fold_done: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut done = 0;
+
+        for entry in &self.entries {
+            if fold_done && matches!(entry.state, State::Finished | State::Skipped(_)) {
+                done += 1;
+                continue;
+            }
+
+            let symbol = match entry.state {
+                State::Pending => '·',
+                State::Running => SPINNER[self.frame % SPINNER.len()],
+                State::Skipped(_) => '-',
+                State::Finished => '✔',
+                State::Failed => '✖'
+            };
+
+            let status = match entry.state {
+                State::Pending => "pending".to_string(),
+                State::Running => entry.started
+                    .map(|started| format_elapsed(started.elapsed()))
+                    .unwrap_or_default(),
+                State::Skipped(reason) => reason.as_str().to_string(),
+                State::Finished | State::Failed => entry.elapsed
+                    .map(format_elapsed)
+                    .unwrap_or_default()
+            };
+
+            lines.push(truncate(&format!("{symbol} {} ({status})", entry.name), width));
+
+            if entry.state == State::Running {
+                for output in &entry.tail {
+                    lines.push(truncate(&format!("    │ {output}"), width));
+                }
+            }
+        }
+
+        if done > 0 {
+            lines.insert(0, truncate(&format!("✔ {done} actions done"), width));
+        }
+
+        lines
+    }
+}
+
+async fn plain(mut events: UnboundedReceiver<Event>, mut out: impl Write) {
+    let mut names: HashMap<Id, String> = HashMap::new();
+
+    while let Some(event) = events.recv().await {
+        match event {
+            Event::RunStarted { actions } => {
+                names.extend(actions);
+            },
+            Event::ActionAdded { id, name, .. } => {
+                let _ = writeln!(out, "[add] {name}");
+                names.insert(id, name);
+            },
+            Event::ActionStarted { id, name } => {
+                let _ = writeln!(out, "[start] {name}");
+                names.insert(id, name);
+            },
+            Event::ActionSkipped { name, reason, .. } => {
+                let _ = writeln!(out, "[skip] {name} ({})", reason.as_str());
+            },
+            Event::ActionFinished { name, elapsed, .. } => {
+                let _ = writeln!(out, "[done] {name} ({})", format_elapsed(elapsed));
+            },
+            Event::ActionFailed { name, elapsed, error, .. } => {
+                let _ = writeln!(out, "[fail] {name} ({}): {error}", format_elapsed(elapsed));
+            },
+            Event::ActionRetried { name, attempt, error, .. } => {
+                let _ = writeln!(out, "[retry] {name} (attempt {attempt}): {error}");
+            },
+            Event::ActionOutput { id, line } => {
+                let name = names.get(&id).map_or("?", String::as_str);
+                let _ = writeln!(out, "[{name}] {line}");
+            },
+            Event::RunFinished { success, elapsed } => {
+                let status = if success { "finished" } else { "failed" };
+                let _ = writeln!(out, "[run] {status} ({})", format_elapsed(elapsed));
+                break;
+            }
+        }
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    format!("{:.1}s", elapsed.as_secs_f64())
+}
+
+/// Get the columns and rows of the terminal on
+/// stdout, falling back to `$COLUMNS` and `$LINES`,
+/// then to 80 by 24.
+fn terminal_size() -> (usize, usize) {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0
+    };
+
+    // SAFETY: `TIOCGWINSZ` only writes to the
+    // `winsize` it is given.
+    let queried = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+
+    if queried && size.ws_col > 0 && size.ws_row > 0 {
+        return (size.ws_col.into(), size.ws_row.into())
+    }
+
+    let variable = |name: &str, default: usize| std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default);
+
+    (variable("COLUMNS", 80), variable("LINES", 24))
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string()
+    }
+
+    let mut truncated = line.chars().take(width.saturating_sub(1)).collect::<String>();
+    truncated.push('…');
+    truncated
+}
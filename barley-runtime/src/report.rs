@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::{Error, Event, Id, SkipReason};
+
+
+/// The final state of an action in a [`Report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The action never started.
+    NotRun,
+    /// The action was still running when the
+    /// workflow stopped.
+    Cancelled,
+    /// The action did not run.
+    Skipped(SkipReason),
+    /// The action finished successfully.
+    Succeeded,
+    /// The action failed.
+    Failed
+}
+
+impl Status {
+    /// Get the name of the status, as used in
+    /// exported reports.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotRun => "not_run",
+            Self::Cancelled => "cancelled",
+            Self::Skipped(SkipReason::UpToDate) => "up_to_date",
+            Self::Skipped(SkipReason::ConditionNotMet) => "condition_not_met",
+            Self::Skipped(SkipReason::DependencyFailed) => "dependency_failed",
+            Self::Skipped(SkipReason::NotTagged) => "not_tagged",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed"
+        }
+    }
+}
+
+/// The result of a single action in a run.
+#[derive(Debug, Clone)]
+pub struct ActionReport {
+    /// The ID of the action.
+    pub id: Id,
+    /// The display name of the action.
+    pub name: String,
+    /// The final state of the action.
+    pub status: Status,
+    /// How long the action ran, if it finished.
+    pub elapsed: Option<Duration>,
+    /// The error returned by the action, if any.
+    pub error: Option<Error>,
+    /// Every line of output the action reported.
+    pub output: Vec<String>
+}
+
+/// A summary of a completed run.
+///
+/// Reports are built from the [`Event`]s of a
+/// runtime, and can be exported as `JUnit` XML or
+/// JSON for use in CI systems.
+///
+/// # Example
+///
+/// ```no_run
+/// use barley_runtime::prelude::*;
+/// use barley_runtime::report::Report;
+///
+/// # async fn run() -> Result<(), barley_runtime::Error> {
+/// let runtime = RuntimeBuilder::new().build();
+/// let mut events = runtime.subscribe();
+///
+/// let result = runtime.perform().await;
+///
+/// let report = Report::from_receiver(&mut events);
+/// report.write_junit("barley.xml", "barley").unwrap();
+///
+/// result
+/// # }
+/// ```
+///
+/// [`Event`]: ../enum.Event.html
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The results of every action, in the order
+    /// that they were first seen.
+    pub actions: Vec<ActionReport>,
+    /// Whether the run succeeded.
+    pub success: bool,
+    /// How long the whole run took.
+    pub elapsed: Duration,
+    index: HashMap<Id, usize>
+}
+
+impl Report {
+    /// Create an empty report.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a report from the events that are
+    /// waiting in a subscription.
+    ///
+    /// This should be called after the run has
+    /// finished, so that every event is available.
+    #[cfg(feature = "async")]
+    pub fn from_receiver(receiver: &mut tokio::sync::mpsc::UnboundedReceiver<Event>) -> Self {
+        std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+    }
+
+    fn action(&mut self, id: Id, name: String) -> &mut ActionReport {
+        let index = *self.index.entry(id).or_insert_with(|| {
+            self.actions.push(ActionReport {
+                id,
+                name,
+                status: Status::NotRun,
+                elapsed: None,
+                error: None,
+                output: Vec::new()
+            });
+
+            self.actions.len() - 1
+        });
+
+        &mut self.actions[index]
+    }
+
+    /// Add an event to the report.
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::RunStarted { actions } => {
+                for (id, name) in actions {
+                    self.action(*id, name.clone());
+                }
+            },
+            Event::ActionAdded { id, name, .. } => {
+                self.action(*id, name.clone());
+            },
+            Event::ActionStarted { id, name } => {
+                // A started action that never reports
+                // back was cancelled by the runtime.
+                self.action(*id, name.clone()).status = Status::Cancelled;
+            },
+            Event::ActionSkipped { id, name, reason } => {
+                self.action(*id, name.clone()).status = Status::Skipped(*reason);
+            },
+            Event::ActionFinished { id, name, elapsed } => {
+                let action = self.action(*id, name.clone());
+                action.status = Status::Succeeded;
+                action.elapsed = Some(*elapsed);
+            },
+            Event::ActionFailed { id, name, elapsed, error } => {
+                let action = self.action(*id, name.clone());
+                action.status = Status::Failed;
+                action.elapsed = Some(*elapsed);
+                action.error = Some(error.clone());
+            },
+            Event::ActionRetried { id, attempt, error, .. } => {
+                if let Some(index) = self.index.get(id) {
+                    self.actions[*index].output.push(format!("attempt {attempt} failed: {error}"));
+                }
+            },
+            Event::ActionOutput { id, line } => {
+                if let Some(index) = self.index.get(id) {
+                    self.actions[*index].output.push(line.clone());
+                }
+            },
+            Event::RunFinished { success, elapsed } => {
+                self.success = *success;
+                self.elapsed = *elapsed;
+            }
+        }
+    }
+
+    /// Count the actions with a given status.
+    #[must_use]
+    pub fn count(&self, status: Status) -> usize {
+        self.actions
+            .iter()
+            .filter(|action| action.status == status)
+            .count()
+    }
+
+    /// Export the report as `JUnit` XML.
+    ///
+    /// Every action becomes a `testcase` in a
+    /// single `testsuite` with the given name.
+    /// Actions that did not run successfully are
+    /// marked as failed or skipped.
+    #[must_use]
+    pub fn to_junit(&self, suite: &str) -> String {
+        let tests = self.actions.len();
+        let failures = self.count(Status::Failed);
+        let skipped = tests - failures - self.count(Status::Succeeded);
+        let time = self.elapsed.as_secs_f64();
+        let suite = escape_xml(suite);
+
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(xml, r#"<testsuites name="{suite}" tests="{tests}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#);
+        let _ = writeln!(xml, r#"  <testsuite name="{suite}" tests="{tests}" failures="{failures}" errors="0" skipped="{skipped}" time="{time:.3}">"#);
+
+        for action in &self.actions {
+            let name = escape_xml(&action.name);
+            let time = action.elapsed.map_or(0.0, |elapsed| elapsed.as_secs_f64());
+
+            let _ = writeln!(xml, r#"    <testcase name="{name}" classname="{suite}" time="{time:.3}">"#);
+
+            match action.status {
+                Status::Failed => {
+                    let (kind, message, detail) = match &action.error {
+                        Some(error) => (
+                            format!("{:?}", error.kind()),
+                            error.message(),
+                            error.detail().unwrap_or(error.message())
+                        ),
+                        None => (String::new(), "", "")
+                    };
+
+                    let _ = writeln!(
+                        xml,
+                        r#"      <failure type="{}" message="{}">{}</failure>"#,
+                        kind,
+                        escape_xml(message),
+                        escape_xml(detail)
+                    );
+                },
+                Status::Skipped(_) | Status::NotRun | Status::Cancelled => {
+                    let _ = writeln!(xml, r#"      <skipped message="{}"/>"#, action.status.as_str());
+                },
+                Status::Succeeded => {}
+            }
+
+            if !action.output.is_empty() {
+                let _ = writeln!(xml, "      <system-out>{}</system-out>", escape_xml(&action.output.join("\n")));
+            }
+
+            let _ = writeln!(xml, "    </testcase>");
+        }
+
+        let _ = writeln!(xml, "  </testsuite>");
+        let _ = writeln!(xml, "</testsuites>");
+
+        xml
+    }
+
+    /// Export the report as a JSON value.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let actions = self.actions
+            .iter()
+            .map(|action| json!({
+                "id": action.id.to_string(),
+                "name": action.name,
+                "status": action.status.as_str(),
+                "elapsed": action.elapsed.map(|elapsed| elapsed.as_secs_f64()),
+                "error": action.error.as_ref().map(|error| json!({
+                    "kind": format!("{:?}", error.kind()),
+                    "message": error.message(),
+                    "detail": error.detail(),
+                    "exit_code": error.exit_code()
+                })),
+                "output": action.output
+            }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "success": self.success,
+            "elapsed": self.elapsed.as_secs_f64(),
+            "actions": actions
+        })
+    }
+
+    /// Write the report to a file as `JUnit` XML.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file
+    /// could not be written.
+    pub fn write_junit<P: AsRef<Path>>(&self, path: P, suite: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_junit(suite))
+    }
+
+    /// Write the report to a file as JSON.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the file
+    /// could not be written.
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        std::fs::write(path, json)
+    }
+}
+
+impl FromIterator<Event> for Report {
+    fn from_iter<I: IntoIterator<Item = Event>>(events: I) -> Self {
+        let mut report = Self::new();
+
+        for event in events {
+            report.record(&event);
+        }
+
+        report
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' => {},
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
@@ -1,12 +1,14 @@
 use tokio::sync::RwLock;
-use tokio::sync::Barrier;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::task::JoinSet;
 
 use std::any::{Any, TypeId};
-use tracing::{debug, info, error};
+use std::future::Future;
+use std::time::Instant;
+use tracing::{debug, info, warn, error, info_span, Instrument};
 use std::{
-    sync::Arc,
-    collections::HashMap
+    sync::{Arc, Mutex},
+    collections::{HashMap, HashSet}
 };
 
 use crate::Operation;
@@ -14,10 +16,22 @@ use crate::context::Context;
 use crate::scope::Scope;
 use crate::action::Node;
 use crate::output::Output;
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
+use crate::event::{Event, SkipReason};
+use crate::scheduler::Scheduler;
+use crate::finalizer::Finalizers;
+use crate::settings::FailurePolicy;
 use crate::Id;
 
 
+/// Actions added to a workflow while it runs.
+#[derive(Default)]
+struct Expansion {
+    pending: Vec<(Option<Id>, Node)>,
+    added: Vec<Node>
+}
+
+
 /// The runtime for a workflow.
 /// 
 /// This struct is used to run a workflow. It contains
@@ -35,10 +49,14 @@ use crate::Id;
 #[derive(Clone)]
 pub struct Runtime {
     ctx: Context,
-    barriers: HashMap<Id, Arc<Barrier>>,
     outputs: Arc<RwLock<HashMap<Id, Output>>>,
     state: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
-    variables: HashMap<String, Arc<dyn Any + Send + Sync>>
+    variables: HashMap<String, Arc<dyn Any + Send + Sync>>,
+    tags: Arc<Vec<String>>,
+    subscribers: Arc<Mutex<Vec<UnboundedSender<Event>>>>,
+    expansion: Arc<Mutex<Expansion>>,
+    finalizers: Finalizers,
+    current: Option<Node>
 }
 
 impl Runtime {
@@ -49,214 +67,505 @@ impl Runtime {
     /// This function will return an error if any of
     /// the actions fail, or if there is an internal
     /// error with the runtime itself.
-    pub async fn perform(mut self) -> Result<(), Error> {
-        let actions = self.ctx.actions.clone();
-        let mut dependents: HashMap<Id, usize> = HashMap::new();
-
-        // Get the dependents for each action. For
-        // example, if action A depends on action B,
-        // then 1 action is dependent on B (A) and 0
-        // actions are dependent on A.
-        for action in &actions {
-            dependents.insert(action.id, 0);
-
-            action.deps()
+    pub async fn perform(self) -> Result<(), Error> {
+        let started = Instant::now();
+
+        self.emit(&Event::RunStarted {
+            actions: self.ctx.actions
                 .iter()
-                .map(Node::id)
-                .for_each(|id| {
-                    let count = dependents.entry(id).or_insert(0);
-                    *count += 1;
-                });
+                .filter(|action| !action.is_hidden())
+                .map(|action| (action.id, action.display_name()))
+                .collect()
+        });
+
+        // Finalizers run even if this future is
+        // dropped before the workflow ends.
+        let guard = self.finalizers.guard();
+        let result = self.clone().perform_actions().await;
+        let finalized = guard.run().await;
+
+        // A failed finalizer must not hide the error
+        // that ended the workflow.
+        let result = result.and(finalized);
+
+        self.emit(&Event::RunFinished {
+            success: result.is_ok(),
+            elapsed: started.elapsed()
+        });
+
+        result
+    }
+
+    async fn perform_actions(self) -> Result<(), Error> {
+        let mut scheduler = Scheduler::default();
+        scheduler.insert(self.ctx.actions.clone(), None);
+
+        let mut join_set: JoinSet<(Id, FailurePolicy, Result<(), Error>)> = JoinSet::new();
+        let mut failed = HashSet::new();
+        let mut first_error = None;
+
+        debug!("Starting actions");
+        loop {
+            self.expand(&mut scheduler);
+
+            while let Some(action) = scheduler.next_ready() {
+                if action.deps().iter().any(|dep| failed.contains(&dep.id)) {
+                    info!("Dependency failed: {}", action.display_name());
+                    self.emit_for(&action, &Event::ActionSkipped {
+                        id: action.id,
+                        name: action.display_name(),
+                        reason: SkipReason::DependencyFailed
+                    });
+
+                    failed.insert(action.id);
+                    scheduler.finish(action.id);
+                    scheduler.release(action.id);
+                    continue;
+                }
+
+                let policy = action.settings().failure_policy.unwrap_or_default();
+                let mut runtime = self.clone();
+                runtime.current = Some(action.clone());
+
+                // Tag everything the action logs with the
+                // node it belongs to.
+                let span = info_span!("action", id = %action.id, name = %action.display_name());
+
+                join_set.spawn(async move {
+                    let result = match runtime.perform_node(&action).await {
+                        Ok(Some(output)) => {
+                            runtime.outputs.write().await.insert(action.id, output);
+                            Ok(())
+                        },
+                        Ok(None) => Ok(()),
+                        Err(err) => Err(err)
+                    };
+
+                    (action.id, policy, result)
+                }.instrument(span));
+            }
+
+            let Some(result) = join_set.join_next().await else {
+                break;
+            };
+
+            let result = match result {
+                Ok((id, FailurePolicy::Ignore, Err(err))) => {
+                    warn!("Ignoring failure: {}", err);
+                    Ok((id, FailurePolicy::Ignore, Ok(())))
+                },
+                Ok((id, FailurePolicy::Continue, Err(err))) => {
+                    failed.insert(id);
+                    first_error.get_or_insert(err);
+                    self.skip_added(id, &mut failed);
+                    Ok((id, FailurePolicy::Continue, Ok(())))
+                },
+                result => result
+            };
+
+            match result {
+                Ok((id, _, Ok(()))) => {
+                    // Add the actions spawned by this one
+                    // before it settles, so that dependents
+                    // also wait for them.
+                    scheduler.finish(id);
+                    self.expand(&mut scheduler);
+                    scheduler.release(id);
+                },
+                Ok((_, _, Err(err))) => {
+                    join_set.abort_all();
+
+                    if let Some(detail) = err.detail() {
+                        println!("{detail}");
+                    }
+
+                    return Err(err)
+                },
+                Err(_) => {
+                    join_set.abort_all();
+
+                    return Err(Error::internal("JOIN_SET_ERROR"))
+                }
+            }
         }
 
-        // Create a barrier for each action that has
-        // any dependents. The barrier will be used
-        // to wait for the dependent actions to finish.
-        for (id, dependents) in dependents.clone() {
-            if dependents == 0 {
-                continue;
+        if let Some(err) = first_error {
+            if let Some(detail) = err.detail() {
+                println!("{detail}");
             }
 
-            let barrier = Arc::new(Barrier::new(dependents + 1));
-            self.barriers.insert(id, barrier);
+            return Err(err)
         }
 
-        let mut join_set: JoinSet<Result<(), Error>> = JoinSet::new();
+        let unsettled = scheduler.unsettled();
 
-        debug!("Starting actions");
-        for action in actions {
-            let runtime_clone = self.clone();
+        if !unsettled.is_empty() {
+            return Err(cycle_error(&unsettled))
+        }
 
-            let action = action.clone();
+        Ok(())
+    }
 
-            let deps = action.deps();
+    /// Move actions added by finished actions into
+    /// the scheduler.
+    fn expand(&self, scheduler: &mut Scheduler) {
+        let Ok(mut expansion) = self.expansion.lock() else {
+            return;
+        };
+
+        let (ready, waiting) = std::mem::take(&mut expansion.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(parent, _)| {
+                parent.is_none_or(|parent| !scheduler.is_running(parent))
+            });
 
-            let barriers = deps
-                .iter()
-                .map(Node::id);
+        expansion.pending = waiting;
+        drop(expansion);
 
-            let barriers = barriers
-                .filter_map(|id| self.barriers.get(&id).cloned())
-                .collect::<Vec<_>>();
+        for (parent, action) in ready {
+            scheduler.insert(vec![action], parent);
+        }
+    }
 
-            let self_barriers = self.barriers.clone();
+    /// Skip the actions added by a failed action, as
+    /// if they depended on it.
+    fn skip_added(&self, parent: Id, failed: &mut HashSet<Id>) {
+        let Ok(mut expansion) = self.expansion.lock() else {
+            return;
+        };
+
+        let (skipped, kept) = std::mem::take(&mut expansion.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(added_by, _)| *added_by == Some(parent));
+
+        expansion.pending = kept;
+        drop(expansion);
+
+        for (_, action) in skipped {
+            info!("Dependency failed: {}", action.display_name());
+            self.emit_for(&action, &Event::ActionSkipped {
+                id: action.id,
+                name: action.display_name(),
+                reason: SkipReason::DependencyFailed
+            });
 
-            join_set.spawn(async move {
-                let self_barrier = self_barriers.get(&action.id).cloned();
+            failed.insert(action.id);
+        }
+    }
 
-                for barrier in barriers {
-                    barrier.wait().await;
-                }
+    /// Probe and run a single node, reporting its
+    /// progress to subscribers.
+    async fn perform_node(&self, action: &Node) -> Result<Option<Output>, Error> {
+        let display_name = action.display_name();
+
+        if !self.is_tagged(action) {
+            info!("Not tagged: {}", display_name);
+            self.emit_for(action, &Event::ActionSkipped {
+                id: action.id,
+                name: display_name,
+                reason: SkipReason::NotTagged
+            });
 
-                let probe = action.probe(runtime_clone.clone()).await?;
-                if !probe.needs_run {
-                    return Ok(())
-                }
+            return Ok(None)
+        }
 
-                let display_name = action.display_name();
-                info!("Starting action: {}", display_name);
+        let should_run = action.should_run(self.clone()).await
+            .map_err(|err| err.attribute(action.id, display_name.clone()))?;
 
-                let output = action.run(runtime_clone.clone(), Operation::Perform).await;
+        if !should_run {
+            info!("Condition not met: {}", display_name);
+            self.emit_for(action, &Event::ActionSkipped {
+                id: action.id,
+                name: display_name,
+                reason: SkipReason::ConditionNotMet
+            });
 
-                if let Err(err) = &output {
-                    error!("Action failed: {}", display_name);
-                    error!("Error: {}", err);
+            return Ok(None)
+        }
 
-                    return Err(err.clone())
-                }
-                
-                info!("Action finished: {}", display_name);
+        let probe = action.probe(self.clone()).await
+            .map_err(|err| err.attribute(action.id, display_name.clone()))?;
 
-                if let Some(barrier) = self_barrier {
-                    barrier.wait().await;
-                }
+        if !probe.needs_run {
+            self.emit_for(action, &Event::ActionSkipped {
+                id: action.id,
+                name: display_name,
+                reason: SkipReason::UpToDate
+            });
 
-                if let Some(output) = output? {
-                    runtime_clone.outputs.write().await.insert(action.id, output);
-                }
+            return Ok(None)
+        }
 
-                Ok(())
+        info!("Starting action: {}", display_name);
+        self.emit_for(action, &Event::ActionStarted {
+            id: action.id,
+            name: display_name.clone()
+        });
+
+        let started = Instant::now();
+        let output = self.run_attempts(action).await
+            .map_err(|err| err.attribute(action.id, display_name.clone()));
+
+        if let Err(err) = &output {
+            error!("Action failed: {}", display_name);
+            error!("Error: {}", err);
+
+            self.emit_for(action, &Event::ActionFailed {
+                id: action.id,
+                name: display_name,
+                elapsed: started.elapsed(),
+                error: err.clone()
             });
+
+            return Err(err.clone())
         }
 
-        while let Some(result) = join_set.join_next().await {
-            match result {
-                Ok(Ok(())) => {},
-                Ok(Err(err)) => {
-                    join_set.abort_all();
+        info!("Action finished: {}", display_name);
+        self.emit_for(action, &Event::ActionFinished {
+            id: action.id,
+            name: display_name,
+            elapsed: started.elapsed()
+        });
 
-                    if let Error::ActionFailed(_, long) = err.clone() {
-                        println!("{long}");
-                    }
+        output
+    }
 
-                    return Err(err)
-                },
-                Err(_) => {
-                    join_set.abort_all();
+    /// Run a node, retrying it as its settings
+    /// allow.
+    async fn run_attempts(&self, action: &Node) -> Result<Option<Output>, Error> {
+        let settings = action.settings();
+        let retries = settings.retries.unwrap_or(0);
+        let mut attempt = 0;
+
+        loop {
+            let result = match settings.timeout {
+                Some(limit) => tokio::time::timeout(limit, action.run(self.clone(), Operation::Perform))
+                    .await
+                    .unwrap_or_else(|_| Err(Error::new(
+                        ErrorKind::TimedOut,
+                        format!("Timed out after {limit:?}")
+                    ))),
+                None => action.run(self.clone(), Operation::Perform).await
+            };
 
-                    return Err(Error::InternalError("JOIN_SET_ERROR"))
-                }
+            match result {
+                Err(err) if attempt < retries => {
+                    attempt += 1;
+                    warn!("Retrying action: {} ({})", action.display_name(), err);
+
+                    self.emit_for(action, &Event::ActionRetried {
+                        id: action.id,
+                        name: action.display_name(),
+                        attempt,
+                        error: err
+                    });
+
+                    if let Some(delay) = settings.retry_delay {
+                        tokio::time::sleep(delay).await;
+                    }
+                },
+                result => return result
             }
         }
-
-        Ok(())
     }
 
     /// Rollback the workflow.
     /// 
     /// This will undo all of the actions that have
-    /// been performed, if possible.
-    /// 
-    /// # Panics
-    /// 
-    /// This action can theoretically panic due to an internal
-    /// unwrapping of guaranteed `Some` variants. In practice,
-    /// this doesn't happen.
+    /// been performed, if possible. Actions are
+    /// rolled back one at a time, in the reverse of
+    /// the order they can run in, so every action is
+    /// rolled back before its dependencies.
     /// 
     /// # Errors
     /// 
     /// This function will return an error if any of
-    /// the actions fail, or if there is an internal
-    /// error with the runtime itself.
+    /// the actions fail, if the actions depend on
+    /// each other in a cycle, or if there is an
+    /// internal error with the runtime itself.
     pub async fn rollback(self) -> Result<(), Error> {
-        let actions = self.ctx.actions.clone();
-        let mut dependencies: HashMap<Id, Vec<Id>> = HashMap::new();
-
         // Check if all of the actions have a rollback
         // function. If not, then the rollback cannot
         // be performed.
-        for action in &actions {
-            if !action.probe(self.clone()).await?.can_rollback {
-                return Err(Error::InternalError("NO_ROLLBACK"))
-            }
+        if !self.can_rollback().await? {
+            return Err(Error::internal("NO_ROLLBACK"))
         }
 
-        // Get the dependencies for each action. For
-        // example, if action A depends on action B,
-        // then B is a dependency of A.
-        for action in &actions {
-            dependencies.insert(action.id, Vec::new());
+        let actions = self.all_actions();
 
-            action.deps()
-                .iter()
-                .map(Node::id)
-                .for_each(|id| {
-                    let deps = dependencies.entry(id).or_insert(Vec::new());
-                    deps.push(action.id);
-                });
+        // Order the actions as they would run, so that
+        // walking the order backwards visits dependents
+        // before their dependencies.
+        let mut scheduler = Scheduler::default();
+        scheduler.insert(actions, None);
+
+        let mut order = Vec::new();
+
+        while let Some(action) = scheduler.next_ready() {
+            scheduler.finish(action.id);
+            scheduler.release(action.id);
+            order.push(action);
         }
 
-        // Sort the actions by their dependencies.
-        let mut actions = actions;
-        actions.sort_by(|a, b| {
-            let a_deps = dependencies.get(&a.id).unwrap();
-            let b_deps = dependencies.get(&b.id).unwrap();
+        let unsettled = scheduler.unsettled();
 
-            if a_deps.contains(&b.id) {
-                return std::cmp::Ordering::Greater
+        if !unsettled.is_empty() {
+            return Err(cycle_error(&unsettled))
+        }
+
+        for action in order.into_iter().rev() {
+            let result = action.run(self.clone(), Operation::Rollback).await
+                .map_err(|err| err.attribute(action.id, action.display_name()));
+
+            if let Err(err) = result {
+                if let Some(detail) = err.detail() {
+                    println!("{detail}");
+                }
+
+                return Err(err)
             }
+        }
+
+        Ok(())
+    }
 
-            if b_deps.contains(&a.id) {
-                return std::cmp::Ordering::Less
+    /// Check whether every action in the workflow,
+    /// including ones added while it ran, can be
+    /// rolled back.
+    pub(crate) async fn can_rollback(&self) -> Result<bool, Error> {
+        for action in self.all_actions() {
+            if !action.probe(self.clone()).await?.can_rollback {
+                return Ok(false)
             }
+        }
+
+        Ok(true)
+    }
 
-            std::cmp::Ordering::Equal
+    fn all_actions(&self) -> Vec<Node> {
+        let mut actions = self.ctx.actions.clone();
+
+        if let Ok(expansion) = self.expansion.lock() {
+            actions.extend(expansion.added.iter().cloned());
+        }
+
+        actions
+    }
+
+    /// Add an action to the running workflow.
+    /// 
+    /// Actions can call this from [`Action::run`]
+    /// to spawn more work once they know what it
+    /// is. The new action runs once its dependencies
+    /// have finished, and is reported like any other
+    /// action. It may depend on the action that
+    /// added it, and on any other action in the
+    /// workflow. Actions that depend on the parent
+    /// also wait for the added action to finish.
+    /// If the parent then fails, the added action
+    /// is skipped.
+    /// 
+    /// The new action belongs to the same scope as
+    /// the action that added it, so it inherits the
+    /// settings, dependencies and name prefix of
+    /// that scope.
+    /// 
+    /// Added actions cannot load state, so any state
+    /// they need must already be in the runtime.
+    /// 
+    /// [`Action::run`]: trait.Action.html#tymethod.run
+    pub fn add_action(&self, mut action: Node) {
+        let parent = self.current.as_ref().map(Node::id);
+
+        if let Some(current) = &self.current {
+            action.adopt(current);
+        }
+
+        self.emit_for(&action, &Event::ActionAdded {
+            id: action.id,
+            name: action.display_name(),
+            parent
         });
 
-        // Create spawns
-        let mut join_set: JoinSet<Result<(), Error>> = JoinSet::new();
+        if let Ok(mut expansion) = self.expansion.lock() {
+            expansion.added.push(action.clone());
+            expansion.pending.push((parent, action));
+        }
+    }
 
-        for action in actions {
-            let runtime_clone = self.clone();
+    /// Register cleanup to run when the workflow
+    /// ends.
+    /// 
+    /// Finalizers run after every action has
+    /// finished, whether the workflow succeeded or
+    /// failed, in the reverse of the order they were
+    /// added. If the workflow is cancelled by
+    /// dropping [`perform`], they run in the
+    /// background instead. A failed finalizer is
+    /// logged, and only fails the workflow if
+    /// nothing else did.
+    /// 
+    /// [`perform`]: #method.perform
+    pub fn add_finalizer<S, F, Fut>(&self, name: S, finalizer: F)
+    where
+        S: Into<String>,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static
+    {
+        self.finalizers.push(name.into(), Box::new(move || Box::pin(finalizer())));
+    }
 
-            join_set.spawn(async move {
-                action.run(runtime_clone.clone(), Operation::Rollback).await?;
+    /// Subscribe to the events of this runtime.
+    /// 
+    /// Every event emitted after this call is sent
+    /// to the returned receiver. Subscribe before
+    /// calling [`perform`] to receive the whole run.
+    /// 
+    /// [`perform`]: #method.perform
+    #[must_use]
+    pub fn subscribe(&self) -> UnboundedReceiver<Event> {
+        let (sender, receiver) = mpsc::unbounded_channel();
 
-                Ok(())
-            });
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
         }
 
-        while let Some(result) = join_set.join_next().await {
-            match result {
-                Ok(Ok(())) => {},
-                Ok(Err(err)) => {
-                    join_set.abort_all();
+        receiver
+    }
 
-                    if let Error::ActionFailed(_, long) = err.clone() {
-                        println!("{long}");
-                    }
+    /// Report a line of output from the running action.
+    /// 
+    /// Actions can use this to share progress with
+    /// subscribers, such as the output of a child
+    /// process. Calls made outside of a running
+    /// action are ignored.
+    pub fn log<S: Into<String>>(&self, line: S) {
+        if let Some(current) = &self.current {
+            self.emit(&Event::ActionOutput {
+                id: current.id,
+                line: line.into()
+            });
+        }
+    }
 
-                    return Err(err)
-                },
-                Err(_) => {
-                    join_set.abort_all();
+    pub(crate) fn emit(&self, event: &Event) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+    }
 
-                    return Err(Error::InternalError("JOIN_SET_ERROR"))
-                }
-            }
+    /// Emit an event about a node, unless the node
+    /// is hidden.
+    fn emit_for(&self, action: &Node, event: &Event) {
+        if !action.is_hidden() {
+            self.emit(event);
         }
+    }
 
-        Ok(())
+    /// Check whether a node has one of the tags
+    /// that the runtime is limited to.
+    fn is_tagged(&self, action: &Node) -> bool {
+        self.tags.is_empty() || action.settings().tags.iter().any(|tag| self.tags.contains(tag))
     }
 
     /// Get the output of an action.
@@ -284,24 +593,89 @@ impl Runtime {
 
     /// Get a variable.
     /// 
-    /// # Panics
-    /// 
-    /// This function will panic if the variable
-    /// is not the type that is requested.
+    /// Returns `None` if the variable is not set,
+    /// or if it is not the type that is requested.
     #[must_use]
-    pub fn get_variable<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
-        self.variables.get("name").cloned().map(|state| {
-            state.downcast::<T>().unwrap()
+    pub fn get_variable<T: Send + Sync + 'static>(&self, name: &str) -> Option<Arc<T>> {
+        self.variables.get(name).cloned().and_then(|value| {
+            value.downcast::<T>().ok()
         })
     }
+
+    pub(crate) fn raw_variable(&self, name: &str) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.variables.get(name).cloned()
+    }
+
+    pub(crate) fn raw_state(&self, id: TypeId) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.state.get(&id).cloned()
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn state_map(&self) -> &HashMap<TypeId, Arc<dyn Any + Send + Sync>> {
+        &self.state
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn has_added_actions(&self) -> bool {
+        self.expansion
+            .lock()
+            .is_ok_and(|expansion| !expansion.added.is_empty())
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn has_finalizers(&self) -> bool {
+        !self.finalizers.is_empty()
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) async fn run_finalizers(&self) -> Result<(), Error> {
+        self.finalizers.run().await
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) async fn outputs_snapshot(&self) -> HashMap<Id, Output> {
+        self.outputs.read().await.clone()
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Create a runtime with no actions, for running
+    /// a single action outside of a workflow.
+    pub(crate) fn detached(
+        outputs: HashMap<Id, Output>,
+        state: HashMap<TypeId, Arc<dyn Any + Send + Sync>>
+    ) -> Self {
+        Self {
+            ctx: Context::new(),
+            outputs: Arc::new(RwLock::new(outputs)),
+            expansion: Arc::new(Mutex::new(Expansion::default())),
+            state,
+            variables: HashMap::new(),
+            tags: Arc::new(Vec::new()),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            finalizers: Finalizers::default(),
+            current: None
+        }
+    }
+}
+
+fn cycle_error(nodes: &[Node]) -> Error {
+    let names = nodes
+        .iter()
+        .map(Node::display_name)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Error::new(ErrorKind::DependencyCycle, format!("Dependency cycle between: {names}"))
 }
 
 /// A builder for a runtime.
+#[derive(Clone)]
 #[allow(clippy::module_name_repetitions)]
 pub struct RuntimeBuilder {
     ctx: Context,
     state: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
-    variables: HashMap<String, Arc<dyn Any + Send + Sync>>
+    variables: HashMap<String, Arc<dyn Any + Send + Sync>>,
+    tags: Vec<String>
 }
 
 impl RuntimeBuilder {
@@ -311,7 +685,8 @@ impl RuntimeBuilder {
         Self {
             ctx: Context::new(),
             state: HashMap::new(),
-            variables: HashMap::new()
+            variables: HashMap::new(),
+            tags: Vec::new()
         }
     }
 
@@ -323,9 +698,14 @@ impl RuntimeBuilder {
     }
 
     /// Add a scope to the runtime.
+    /// 
+    /// Inner scopes are added as well. See
+    /// [`Scope::flatten`].
+    /// 
+    /// [`Scope::flatten`]: struct.Scope.html#method.flatten
     pub async fn add_scope(mut self, scope: Scope) -> Self {
-        for action in scope.actions() {
-            self = self.add_action(action.clone()).await;
+        for action in scope.flatten() {
+            self = self.add_action(action).await;
         }
 
         self
@@ -336,10 +716,23 @@ impl RuntimeBuilder {
     pub fn build(self) -> Runtime {
         Runtime {
             ctx: self.ctx,
-            barriers: HashMap::new(),
             outputs: Arc::new(RwLock::new(HashMap::new())),
+            expansion: Arc::new(Mutex::new(Expansion::default())),
             state: self.state,
-            variables: HashMap::new()
+            variables: self.variables,
+            tags: Arc::new(self.tags),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            finalizers: Finalizers::default(),
+            current: None
+        }
+    }
+
+    /// Build the runtime with outputs already set.
+    #[cfg(feature = "testing")]
+    pub(crate) fn build_with_outputs(self, outputs: HashMap<Id, Output>) -> Runtime {
+        Runtime {
+            outputs: Arc::new(RwLock::new(outputs)),
+            ..self.build()
         }
     }
 
@@ -349,6 +742,29 @@ impl RuntimeBuilder {
         self
     }
 
+    /// Get the state object of a type.
+    /// 
+    /// Returns `None` if no state of that type has
+    /// been added.
+    #[must_use]
+    pub fn get_state<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.state.get(&TypeId::of::<T>()).cloned().and_then(|state| {
+            state.downcast::<T>().ok()
+        })
+    }
+
+    /// Check whether a state object of a type has
+    /// been added.
+    /// 
+    /// Actions can use this in [`Action::load_state`]
+    /// to avoid loading shared state twice.
+    /// 
+    /// [`Action::load_state`]: trait.Action.html#method.load_state
+    #[must_use]
+    pub fn has_state<T: Send + Sync + 'static>(&self) -> bool {
+        self.state.contains_key(&TypeId::of::<T>())
+    }
+
     /// Set a variable.
     pub fn set_variable<T: Send + Sync + 'static>(&mut self, name: &str, value: T) -> &mut Self {
         self.variables.insert(name.to_string(), Arc::new(value));
@@ -357,16 +773,50 @@ impl RuntimeBuilder {
 
     /// Get a variable.
     /// 
-    /// # Panics
-    /// 
-    /// This function will panic if the variable
-    /// is not the type that is requested.
+    /// Returns `None` if the variable is not set,
+    /// or if it is not the type that is requested.
     #[must_use]
-    pub fn get_variable<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
-        self.variables.get("name").cloned().map(|state| {
-            state.downcast::<T>().unwrap()
+    pub fn get_variable<T: Send + Sync + 'static>(&self, name: &str) -> Option<Arc<T>> {
+        self.variables.get(name).cloned().and_then(|value| {
+            value.downcast::<T>().ok()
         })
     }
+
+    /// Only run actions with at least one of these
+    /// tags.
+    /// 
+    /// Other actions are skipped, and actions that
+    /// depend on them still run. Tags are set in
+    /// [`Settings::tags`], and actions inherit the
+    /// tags of their scopes. By default, every
+    /// action runs.
+    /// 
+    /// [`Settings::tags`]: struct.Settings.html#structfield.tags
+    pub fn only_tags<I, S>(&mut self, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        self.tags.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    pub(crate) fn insert_node(&mut self, action: Node) {
+        self.ctx.add_action(action);
+    }
+
+    pub(crate) fn insert_raw_variable(&mut self, name: String, value: Arc<dyn Any + Send + Sync>) {
+        self.variables.insert(name, value);
+    }
+
+    pub(crate) fn insert_raw_state(&mut self, id: TypeId, state: Arc<dyn Any + Send + Sync>) {
+        self.state.insert(id, state);
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn state_map(&self) -> &HashMap<TypeId, Arc<dyn Any + Send + Sync>> {
+        &self.state
+    }
 }
 
 impl Default for RuntimeBuilder {
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::action::Node;
+use crate::Id;
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Waiting,
+    Running,
+    Ran,
+    Settled
+}
+
+struct Entry {
+    node: Node,
+    parent: Option<Id>,
+    state: State,
+    remaining: usize,
+    outstanding: usize
+}
+
+/// Tracks which nodes of a workflow can run.
+///
+/// A node is ready once all of its dependencies
+/// have settled. A node settles once it has run
+/// and every node it added while running has
+/// settled too, so dependents of a node also wait
+/// for the actions that it spawned. Nodes added
+/// by a parent only wait for that parent to run.
+#[derive(Default)]
+pub(crate) struct Scheduler {
+    entries: HashMap<Id, Entry>,
+    dependents: HashMap<Id, Vec<Id>>,
+    ready: VecDeque<Id>
+}
+
+impl Scheduler {
+    /// Add nodes to the graph.
+    ///
+    /// Every node is added before dependencies are
+    /// resolved, so nodes in the same batch can
+    /// depend on each other. Dependencies that are
+    /// not part of the graph are ignored.
+    pub(crate) fn insert(&mut self, nodes: Vec<Node>, parent: Option<Id>) {
+        let mut inserted = Vec::new();
+
+        for node in nodes {
+            if self.entries.contains_key(&node.id) {
+                continue;
+            }
+
+            if let Some(parent) = parent.and_then(|parent| self.entries.get_mut(&parent)) {
+                parent.outstanding += 1;
+            }
+
+            inserted.push(node.id);
+            self.entries.insert(node.id, Entry {
+                node,
+                parent,
+                state: State::Waiting,
+                remaining: 0,
+                outstanding: 0
+            });
+        }
+
+        for id in inserted {
+            let mut remaining = 0;
+
+            for dep in self.entries[&id].node.deps() {
+                let Some(entry) = self.entries.get(&dep.id) else {
+                    continue;
+                };
+
+                let satisfied = match entry.state {
+                    State::Settled => true,
+                    State::Ran => self.is_ancestor(dep.id, id),
+                    State::Waiting | State::Running => false
+                };
+
+                if !satisfied {
+                    remaining += 1;
+                    self.dependents.entry(dep.id).or_default().push(id);
+                }
+            }
+
+            if let Some(entry) = self.entries.get_mut(&id) {
+                entry.remaining = remaining;
+            }
+
+            if remaining == 0 {
+                self.ready.push_back(id);
+            }
+        }
+    }
+
+    /// Take the next node that is ready to run.
+    pub(crate) fn next_ready(&mut self) -> Option<Node> {
+        let id = self.ready.pop_front()?;
+        let entry = self.entries.get_mut(&id)?;
+
+        entry.state = State::Running;
+        Some(entry.node.clone())
+    }
+
+    /// Check whether a node is currently running.
+    pub(crate) fn is_running(&self, id: Id) -> bool {
+        self.entries
+            .get(&id)
+            .is_some_and(|entry| entry.state == State::Running)
+    }
+
+    /// Mark a node as having run.
+    ///
+    /// Any actions that the node added should be
+    /// inserted before calling [`release`], so that
+    /// its dependents wait for them.
+    ///
+    /// [`release`]: #method.release
+    pub(crate) fn finish(&mut self, id: Id) {
+        if let Some(entry) = self.entries.get_mut(&id) {
+            entry.state = State::Ran;
+        }
+    }
+
+    /// Settle a node that has run, unless it is
+    /// still waiting on actions that it added.
+    pub(crate) fn release(&mut self, id: Id) {
+        let done = self.entries
+            .get(&id)
+            .is_some_and(|entry| entry.state == State::Ran && entry.outstanding == 0);
+
+        if done {
+            self.settle(id);
+        }
+    }
+
+    /// List the nodes that have not settled.
+    pub(crate) fn unsettled(&self) -> Vec<Node> {
+        self.entries
+            .values()
+            .filter(|entry| entry.state != State::Settled)
+            .map(|entry| entry.node.clone())
+            .collect()
+    }
+
+    fn settle(&mut self, id: Id) {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let parent = match self.entries.get_mut(&id) {
+                Some(entry) => {
+                    entry.state = State::Settled;
+                    entry.parent
+                },
+                None => continue
+            };
+
+            for dependent in self.dependents.remove(&id).unwrap_or_default() {
+                if let Some(entry) = self.entries.get_mut(&dependent) {
+                    entry.remaining -= 1;
+
+                    if entry.remaining == 0 {
+                        self.ready.push_back(dependent);
+                    }
+                }
+            }
+
+            if let Some(entry) = parent.and_then(|parent| self.entries.get_mut(&parent)) {
+                entry.outstanding -= 1;
+
+                if entry.state == State::Ran && entry.outstanding == 0 {
+                    stack.push(entry.node.id);
+                }
+            }
+        }
+    }
+
+    fn is_ancestor(&self, ancestor: Id, id: Id) -> bool {
+        let mut current = self.entries.get(&id).and_then(|entry| entry.parent);
+
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true
+            }
+
+            current = self.entries.get(&parent).and_then(|entry| entry.parent);
+        }
+
+        false
+    }
+}
@@ -1,28 +1,74 @@
-use crate::action::Node;
+use async_trait::async_trait;
+use std::sync::Arc;
+use crate::action::{Action, Node};
+use crate::condition::Condition;
+use crate::matrix::Matrix;
+use crate::module::{Module, Exports};
+use crate::output::Output;
+use crate::settings::Settings;
+use crate::{Runtime, Error, Probe, Operation};
 
 
 /// A collection of actions.
-/// 
-/// This does not provide any extra action
-/// execution context, but it is useful
-/// for simplifying the creation of
-/// runtimes with many actions.
-#[derive(Default, Clone)]
+///
+/// Scopes group actions so that they can be
+/// treated as one unit. A scope can contain
+/// other scopes, prefix the names of its
+/// actions, depend on other actions, and carry
+/// [`Settings`] that its actions inherit.
+///
+/// Every scope also has a node that runs after
+/// all of its actions, returned by [`as_node`].
+/// Depending on that node depends on the whole
+/// scope. The node does not show up in events
+/// or reports.
+///
+/// [`Settings`]: struct.Settings.html
+/// [`as_node`]: #method.as_node
+#[derive(Clone)]
 pub struct Scope {
-    actions: Vec<Node>
+    name: Option<String>,
+    actions: Vec<Node>,
+    scopes: Vec<Scope>,
+    deps: Vec<Node>,
+    settings: Settings,
+    exit: Node
 }
 
 impl Scope {
     /// Create a new scope.
     #[must_use]
     pub fn new() -> Self {
+        let mut exit: Node = Join.into();
+        exit.hide();
+
         Self {
-            actions: Vec::new()
+            name: None,
+            actions: Vec::new(),
+            scopes: Vec::new(),
+            deps: Vec::new(),
+            settings: Settings::default(),
+            exit
         }
     }
 
+    /// Create a new scope with a name.
+    ///
+    /// The name is added in front of the name of
+    /// every action in the scope, like
+    /// `setup/Install curl`.
+    #[must_use]
+    pub fn named<S: Into<String>>(name: S) -> Self {
+        let name = name.into();
+        let mut scope = Self::new();
+
+        scope.exit.set_name(name.clone());
+        scope.name = Some(name);
+        scope
+    }
+
     /// Add an action to the scope.
-    /// 
+    ///
     /// The action object will be returned
     /// so that it can be used to add
     /// dependencies.
@@ -32,9 +78,177 @@ impl Scope {
         action
     }
 
+    /// Add a scope inside this one.
+    ///
+    /// The node of the inner scope is returned,
+    /// so that other actions can depend on the
+    /// whole scope.
+    pub fn add_scope(&mut self, scope: Scope) -> Node {
+        let node = scope.as_node();
+        self.scopes.push(scope);
+        node
+    }
+
+    /// Build a module and add it inside this scope.
+    ///
+    /// The exported nodes of the module are
+    /// returned.
+    pub fn add_module<M: Module>(&mut self, module: &M, params: M::Params) -> Exports {
+        let (scope, exports) = module.build(params).into_parts();
+        self.scopes.push(scope);
+        exports
+    }
+
+    /// Make every action in the scope depend on
+    /// another action.
+    ///
+    /// This applies to every action in the scope,
+    /// including ones added later and ones in
+    /// inner scopes. To depend on another scope,
+    /// pass its [`as_node`].
+    ///
+    /// [`as_node`]: #method.as_node
+    pub fn requires(&mut self, action: &Node) {
+        if self.deps.iter().all(|dep| dep.id != action.id) {
+            self.deps.push(action.clone());
+        }
+    }
+
+    /// Only run the actions in the scope if a
+    /// condition is met.
+    ///
+    /// This only applies to actions that are
+    /// already in the scope, including ones in
+    /// inner scopes. See [`Node::run_if`].
+    ///
+    /// [`Node::run_if`]: struct.Node.html#method.run_if
+    pub fn run_if<C: Condition + 'static>(&mut self, condition: C) {
+        let condition: Arc<dyn Condition> = Arc::new(condition);
+        self.apply_condition(&condition);
+    }
+
+    fn apply_condition(&mut self, condition: &Arc<dyn Condition>) {
+        for node in &mut self.actions {
+            node.run_if(condition.clone());
+        }
+
+        for scope in &mut self.scopes {
+            scope.apply_condition(condition);
+        }
+    }
+
+    /// Start building a matrix of actions.
+    ///
+    /// The factory is called with one value from
+    /// each axis, for every combination of values.
+    /// See [`Matrix`].
+    ///
+    /// [`Matrix`]: struct.Matrix.html
+    pub fn matrix<F, A>(&mut self, factory: F) -> Matrix<'_>
+    where
+        F: Fn(&[Output]) -> A + Send + Sync + 'static,
+        A: Into<Node>
+    {
+        Matrix::new(self, Arc::new(move |params| factory(params).into()))
+    }
+
+    /// Get the settings of the scope.
+    #[must_use]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Change the settings of the scope.
+    ///
+    /// Actions and inner scopes inherit any
+    /// setting that they do not set themselves.
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Get a node that stands for the whole scope.
+    ///
+    /// The node runs once every action in the
+    /// scope has run, so other actions and scopes
+    /// can depend on it.
+    #[must_use]
+    pub fn as_node(&self) -> Node {
+        self.exit.clone()
+    }
+
     /// List the actions in the scope.
+    ///
+    /// This does not include inner scopes. See
+    /// [`flatten`].
+    ///
+    /// [`flatten`]: #method.flatten
     #[must_use]
     pub fn actions(&self) -> &[Node] {
         &self.actions
     }
+
+    /// List every node in the scope, ready to be
+    /// added to a runtime.
+    ///
+    /// This includes the actions of inner scopes
+    /// and the node of every scope, with names,
+    /// dependencies and settings applied.
+    #[must_use]
+    pub fn flatten(&self) -> Vec<Node> {
+        let mut nodes = self.actions.clone();
+
+        for scope in &self.scopes {
+            nodes.extend(scope.flatten());
+        }
+
+        for node in &mut nodes {
+            node.settings_mut().inherit(&self.settings);
+
+            for dep in &self.deps {
+                node.requires(dep.clone());
+            }
+
+            if let Some(name) = &self.name {
+                node.enter_scope(name);
+            }
+        }
+
+        let mut exit = self.exit.clone();
+        exit.settings_mut().inherit(&self.settings);
+
+        for dep in self.deps.iter().chain(&nodes) {
+            exit.requires(dep.clone());
+        }
+
+        nodes.push(exit);
+        nodes
+    }
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The node that runs after every action in a
+/// scope.
+struct Join;
+
+#[async_trait]
+impl Action for Join {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        "Scope".to_string()
+    }
 }
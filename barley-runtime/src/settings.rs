@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+
+/// What the runtime does when an action fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// Stop the whole workflow.
+    #[default]
+    Abort,
+    /// Skip everything that depends on the action,
+    /// but keep running the rest of the workflow.
+    /// The workflow still fails once it is done.
+    Continue,
+    /// Treat the action as if it had succeeded.
+    Ignore
+}
+
+/// Settings for running a node.
+///
+/// Settings that are not set on a node are
+/// inherited from the [`Scope`] it belongs to,
+/// and from the scopes around that one. Tags are
+/// combined instead.
+///
+/// [`Scope`]: struct.Scope.html
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// How many times to retry the action after
+    /// it fails.
+    pub retries: Option<u32>,
+    /// How long to wait between retries.
+    pub retry_delay: Option<Duration>,
+    /// How long each attempt may run before it
+    /// fails.
+    pub timeout: Option<Duration>,
+    /// What to do when the action fails.
+    pub failure_policy: Option<FailurePolicy>,
+    /// Labels for the action.
+    ///
+    /// When the runtime is limited to some tags
+    /// with [`RuntimeBuilder::only_tags`], actions
+    /// without any of them are skipped.
+    ///
+    /// [`RuntimeBuilder::only_tags`]: struct.RuntimeBuilder.html#method.only_tags
+    pub tags: Vec<String>
+}
+
+impl Settings {
+    /// Fill in anything that is not set from
+    /// another set of settings.
+    pub(crate) fn inherit(&mut self, parent: &Settings) {
+        self.retries = self.retries.or(parent.retries);
+        self.retry_delay = self.retry_delay.or(parent.retry_delay);
+        self.timeout = self.timeout.or(parent.timeout);
+        self.failure_policy = self.failure_policy.or(parent.failure_policy);
+
+        for tag in &parent.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+    }
+}
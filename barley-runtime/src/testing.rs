@@ -0,0 +1,418 @@
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use crate::{
+    Action, Runtime, RuntimeBuilder, Node,
+    Output, Error, Probe, Operation, Id
+};
+
+
+/// Builds a [`Runtime`] for calling a single action
+/// directly.
+///
+/// Outputs, state and variables are set up front,
+/// so an action under test can be probed and run
+/// without adding its dependencies to a workflow.
+///
+/// # Example
+///
+/// ```no_run
+/// use barley_runtime::prelude::*;
+/// use barley_runtime::testing::{FakeAction, MockRuntime};
+///
+/// # async fn test(action: impl Action) -> Result<(), barley_runtime::Error> {
+/// let dependency: barley_runtime::Node = FakeAction::new("Dependency").into();
+///
+/// let runtime = MockRuntime::new()
+///     .output(&dependency, "1.2.3")
+///     .variable("environment", "staging".to_string())
+///     .build();
+///
+/// let output = action.run(runtime, Operation::Perform).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Runtime`]: ../struct.Runtime.html
+#[derive(Default)]
+pub struct MockRuntime {
+    builder: RuntimeBuilder,
+    outputs: HashMap<Id, Output>
+}
+
+impl MockRuntime {
+    /// Create an empty mock runtime.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the output of a node.
+    #[must_use]
+    pub fn output<O: Into<Output>>(mut self, node: &Node, output: O) -> Self {
+        self.outputs.insert(node.id(), output.into());
+        self
+    }
+
+    /// Add a state object.
+    #[must_use]
+    pub fn state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.builder.add_state(state);
+        self
+    }
+
+    /// Set a variable.
+    #[must_use]
+    pub fn variable<T: Send + Sync + 'static>(mut self, name: &str, value: T) -> Self {
+        self.builder.set_variable(name, value);
+        self
+    }
+
+    /// Build the runtime.
+    #[must_use]
+    pub fn build(self) -> Runtime {
+        self.builder.build_with_outputs(self.outputs)
+    }
+}
+
+/// An action with scripted results.
+///
+/// Scripted results are used in the order they
+/// were added, one per call. Once they run out,
+/// the default result is used. By default, a fake
+/// action needs to run, can be rolled back, and
+/// returns no output.
+///
+/// Attach a [`Recorder`] to see when the runtime
+/// ran the action.
+///
+/// [`Recorder`]: struct.Recorder.html
+pub struct FakeAction {
+    name: String,
+    probe: Result<Probe, Error>,
+    probes: Mutex<VecDeque<Result<Probe, Error>>>,
+    result: Result<Option<Output>, Error>,
+    results: Mutex<VecDeque<Result<Option<Output>, Error>>>,
+    delay: Option<Duration>,
+    recorder: Option<Recorder>
+}
+
+impl FakeAction {
+    /// Create a fake action.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            probe: Ok(Probe {
+                needs_run: true,
+                can_rollback: true
+            }),
+            probes: Mutex::new(VecDeque::new()),
+            result: Ok(None),
+            results: Mutex::new(VecDeque::new()),
+            delay: None,
+            recorder: None
+        }
+    }
+
+    /// Return an output from every run.
+    #[must_use]
+    pub fn returns<O: Into<Output>>(mut self, output: O) -> Self {
+        self.result = Ok(Some(output.into()));
+        self
+    }
+
+    /// Fail every run.
+    #[must_use]
+    pub fn fails(mut self, error: Error) -> Self {
+        self.result = Err(error);
+        self
+    }
+
+    /// Script the result of the next run.
+    #[must_use]
+    pub fn run_once(self, result: Result<Option<Output>, Error>) -> Self {
+        lock(&self.results).push_back(result);
+        self
+    }
+
+    /// Return a probe from every probe call.
+    #[must_use]
+    pub fn probe(mut self, probe: Probe) -> Self {
+        self.probe = Ok(probe);
+        self
+    }
+
+    /// Script the result of the next probe call.
+    #[must_use]
+    pub fn probe_once(self, result: Result<Probe, Error>) -> Self {
+        lock(&self.probes).push_back(result);
+        self
+    }
+
+    /// Report that the action does not need to run.
+    #[must_use]
+    pub fn up_to_date(self) -> Self {
+        self.probe(Probe {
+            needs_run: false,
+            can_rollback: true
+        })
+    }
+
+    /// Report that the action cannot be rolled back.
+    #[must_use]
+    pub fn no_rollback(self) -> Self {
+        let needs_run = self.probe.as_ref().map_or(true, |probe| probe.needs_run);
+
+        self.probe(Probe {
+            needs_run,
+            can_rollback: false
+        })
+    }
+
+    /// Wait before finishing each run.
+    #[must_use]
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Record each run in a recorder.
+    #[must_use]
+    pub fn recorder(mut self, recorder: &Recorder) -> Self {
+        self.recorder = Some(recorder.clone());
+        self
+    }
+}
+
+#[async_trait]
+impl Action for FakeAction {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        lock(&self.probes)
+            .pop_front()
+            .unwrap_or_else(|| self.probe.clone())
+    }
+
+    async fn run(&self, _runtime: Runtime, operation: Operation) -> Result<Option<Output>, Error> {
+        let started = Instant::now();
+
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let result = if matches!(operation, Operation::Rollback) {
+            Ok(None)
+        } else {
+            lock(&self.results)
+                .pop_front()
+                .unwrap_or_else(|| self.result.clone())
+        };
+
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Record {
+                name: self.name.clone(),
+                operation,
+                started,
+                finished: Instant::now(),
+                succeeded: result.is_ok()
+            });
+        }
+
+        result
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// One run of a [`FakeAction`].
+///
+/// [`FakeAction`]: struct.FakeAction.html
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// The name of the action.
+    pub name: String,
+    /// Whether the action was performed or rolled
+    /// back.
+    pub operation: Operation,
+    /// When the run started.
+    pub started: Instant,
+    /// When the run finished.
+    pub finished: Instant,
+    /// Whether the run succeeded.
+    pub succeeded: bool
+}
+
+impl Record {
+    fn overlaps(&self, other: &Record) -> bool {
+        self.started < other.finished && other.started < self.finished
+    }
+}
+
+/// Records the runs of fake actions.
+///
+/// Clones of a recorder share their records, so
+/// one recorder can be attached to every action
+/// in a workflow and checked after it has run.
+///
+/// # Example
+///
+/// ```no_run
+/// use barley_runtime::prelude::*;
+/// use barley_runtime::testing::{FakeAction, Recorder};
+///
+/// # async fn test() -> Result<(), barley_runtime::Error> {
+/// let recorder = Recorder::new();
+///
+/// let first: barley_runtime::Node = FakeAction::new("First").recorder(&recorder).into();
+/// let mut second: barley_runtime::Node = FakeAction::new("Second").recorder(&recorder).into();
+/// second.requires(first.clone());
+///
+/// RuntimeBuilder::new()
+///     .add_action(first)
+///     .await
+///     .add_action(second)
+///     .await
+///     .build()
+///     .perform()
+///     .await?;
+///
+/// recorder.assert_before("First", "Second");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    records: Arc<Mutex<Vec<Record>>>
+}
+
+impl Recorder {
+    /// Create an empty recorder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, record: Record) {
+        lock(&self.records).push(record);
+    }
+
+    /// Get every record, in the order the runs
+    /// started.
+    #[must_use]
+    pub fn records(&self) -> Vec<Record> {
+        let mut records = lock(&self.records).clone();
+        records.sort_by_key(|record| record.started);
+        records
+    }
+
+    fn performed(&self) -> Vec<Record> {
+        self.records()
+            .into_iter()
+            .filter(|record| matches!(record.operation, Operation::Perform))
+            .collect()
+    }
+
+    fn find(&self, name: &str) -> Record {
+        self.performed()
+            .into_iter()
+            .find(|record| record.name == name)
+            .unwrap_or_else(|| panic!("{name} was not performed"))
+    }
+
+    /// Get the names of the performed actions, in
+    /// the order they started.
+    #[must_use]
+    pub fn order(&self) -> Vec<String> {
+        self.performed()
+            .into_iter()
+            .map(|record| record.name)
+            .collect()
+    }
+
+    /// Get the names of the rolled back actions, in
+    /// the order they started.
+    #[must_use]
+    pub fn rollback_order(&self) -> Vec<String> {
+        self.records()
+            .into_iter()
+            .filter(|record| matches!(record.operation, Operation::Rollback))
+            .map(|record| record.name)
+            .collect()
+    }
+
+    /// Get the largest number of actions that were
+    /// performed at the same time.
+    #[must_use]
+    pub fn max_concurrency(&self) -> usize {
+        let performed = self.performed();
+
+        performed
+            .iter()
+            .map(|record| {
+                performed
+                    .iter()
+                    .filter(|other| other.started <= record.started && record.started < other.finished)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Check that one action finished before another
+    /// started.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if either action was not
+    /// performed, or if they overlapped or ran in
+    /// the other order.
+    pub fn assert_before(&self, first: &str, second: &str) {
+        let (a, b) = (self.find(first), self.find(second));
+
+        assert!(
+            a.finished <= b.started,
+            "expected {first} to finish before {second} started"
+        );
+    }
+
+    /// Check that two actions ran at the same time.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if either action was not
+    /// performed, or if they did not overlap.
+    pub fn assert_concurrent(&self, first: &str, second: &str) {
+        let (a, b) = (self.find(first), self.find(second));
+
+        assert!(
+            a.overlaps(&b),
+            "expected {first} and {second} to run at the same time"
+        );
+    }
+
+    /// Check the order of the performed actions.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the actions did not
+    /// start in exactly this order.
+    pub fn assert_order(&self, expected: &[&str]) {
+        assert_eq!(self.order(), expected, "unexpected order of actions");
+    }
+
+    /// Check the order of the rolled back actions.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the actions were not
+    /// rolled back in exactly this order.
+    pub fn assert_rollback_order(&self, expected: &[&str]) {
+        assert_eq!(self.rollback_order(), expected, "unexpected order of rollbacks");
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
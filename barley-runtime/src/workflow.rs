@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use crate::{
+    Action, Runtime, RuntimeBuilder, Node,
+    Output, Error, Probe, Operation,
+    Scope, Event
+};
+
+
+/// Runs a whole workflow as a single node.
+///
+/// The child workflow gets its own runtime, so
+/// its actions only see the variables and state
+/// that are forwarded from the parent. Outputs
+/// of the child that are exported are returned
+/// together as an [`Output::Map`], keyed by the
+/// export name.
+///
+/// Output from the child's actions is reported
+/// as output of this node. Rolling this node back
+/// rolls back the child workflow, so it can only
+/// be rolled back if every action of the child
+/// can.
+///
+/// # Example
+///
+/// ```no_run
+/// use barley_runtime::prelude::*;
+/// use barley_runtime::SubWorkflow;
+///
+/// # async fn run(scope: Scope, node: barley_runtime::Node) -> Result<(), barley_runtime::Error> {
+/// let deploy = SubWorkflow::from_scope("Deploy", &scope)
+///     .forward_variable("environment")
+///     .export("version", node);
+///
+/// let runtime = RuntimeBuilder::new()
+///     .add_action(deploy.into())
+///     .await
+///     .build();
+///
+/// runtime.perform().await
+/// # }
+/// ```
+///
+/// [`Output::Map`]: enum.Output.html#variant.Map
+pub struct SubWorkflow {
+    name: String,
+    builder: RuntimeBuilder,
+    variables: Vec<String>,
+    state: Vec<TypeId>,
+    exports: Vec<(String, Node)>,
+    child: Mutex<Option<Runtime>>
+}
+
+impl SubWorkflow {
+    /// Wrap a runtime builder.
+    pub fn new<S: Into<String>>(name: S, builder: RuntimeBuilder) -> Self {
+        Self {
+            name: name.into(),
+            builder,
+            variables: Vec::new(),
+            state: Vec::new(),
+            exports: Vec::new(),
+            child: Mutex::new(None)
+        }
+    }
+
+    /// Wrap a scope.
+    ///
+    /// Unlike [`RuntimeBuilder::add_scope`], this
+    /// does not load the state of the actions in
+    /// the scope. Use [`forward_state`] for any
+    /// state they need.
+    ///
+    /// [`RuntimeBuilder::add_scope`]: struct.RuntimeBuilder.html#method.add_scope
+    /// [`forward_state`]: #method.forward_state
+    pub fn from_scope<S: Into<String>>(name: S, scope: &Scope) -> Self {
+        let mut builder = RuntimeBuilder::new();
+
+        for node in scope.flatten() {
+            builder.insert_node(node);
+        }
+
+        Self::new(name, builder)
+    }
+
+    /// Copy a variable from the parent runtime into
+    /// the child when it runs.
+    ///
+    /// Variables that are not set in the parent
+    /// are left alone.
+    #[must_use]
+    pub fn forward_variable<S: Into<String>>(mut self, name: S) -> Self {
+        self.variables.push(name.into());
+        self
+    }
+
+    /// Copy a state object from the parent runtime
+    /// into the child when it runs.
+    #[must_use]
+    pub fn forward_state<T: Send + Sync + 'static>(mut self) -> Self {
+        self.state.push(TypeId::of::<T>());
+        self
+    }
+
+    /// Return the output of a child node under a
+    /// name.
+    ///
+    /// Nodes that return nothing are left out of
+    /// the map.
+    #[must_use]
+    pub fn export<S: Into<String>>(mut self, name: S, node: Node) -> Self {
+        self.exports.push((name.into(), node));
+        self
+    }
+
+    fn build(&self, parent: &Runtime) -> Runtime {
+        let mut builder = self.builder.clone();
+
+        for name in &self.variables {
+            if let Some(value) = parent.raw_variable(name) {
+                builder.insert_raw_variable(name.clone(), value);
+            }
+        }
+
+        for id in &self.state {
+            if let Some(state) = parent.raw_state(*id) {
+                builder.insert_raw_state(*id, state);
+            }
+        }
+
+        builder.build()
+    }
+}
+
+#[async_trait]
+impl Action for SubWorkflow {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        let child = self.child
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+
+        // Until the child has run, there is nothing
+        // to roll back.
+        let can_rollback = match child {
+            Some(child) => child.can_rollback().await?,
+            None => true
+        };
+
+        Ok(Probe {
+            needs_run: true,
+            can_rollback
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, operation: Operation) -> Result<Option<Output>, Error> {
+        if matches!(operation, Operation::Rollback) {
+            let child = self.child
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+
+            if let Some(child) = child {
+                child.rollback().await?;
+            }
+
+            return Ok(None)
+        }
+
+        let child = self.build(&runtime);
+        let mut events = child.subscribe();
+
+        *self.child.lock().unwrap_or_else(PoisonError::into_inner) = Some(child.clone());
+
+        let forward = tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                match event {
+                    Event::ActionStarted { name, .. } => runtime.log(format!("started {name}")),
+                    Event::ActionFinished { name, .. } => runtime.log(format!("finished {name}")),
+                    Event::ActionFailed { name, error, .. } => runtime.log(format!("failed {name}: {error}")),
+                    Event::ActionOutput { line, .. } => runtime.log(line),
+                    Event::RunFinished { .. } => break,
+                    _ => {}
+                }
+            }
+        });
+
+        let result = child.clone().perform().await;
+        let _ = forward.await;
+        result?;
+
+        let mut outputs = BTreeMap::new();
+
+        for (name, node) in &self.exports {
+            if let Some(output) = child.get_output(node.clone()).await {
+                outputs.insert(name.clone(), output);
+            }
+        }
+
+        Ok(Some(Output::Map(outputs)))
+    }
+
+    fn display_name(&self) -> String {
+        self.name.clone()
+    }
+}
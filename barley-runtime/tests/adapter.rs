@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_trait::async_trait;
+use barley_runtime::adapter::to_blocking;
+use barley_runtime::{
+    blocking, Action, ErrorKind, Error, Node,
+    Operation, Output, Probe, Runtime
+};
+
+
+/// Adds another copy of itself to the workflow.
+struct Expand;
+
+#[async_trait]
+impl Action for Expand {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        runtime.add_action(Expand.into());
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        "Expand".to_string()
+    }
+}
+
+#[test]
+fn rejects_added_actions() {
+    let node: Node = Expand.into();
+
+    let err = blocking::RuntimeBuilder::new()
+        .add_action(to_blocking(&node))
+        .build()
+        .perform()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::OperationNotSupported);
+    assert!(err.message().contains("cannot add actions"));
+}
+
+/// Registers a finalizer that sets a flag.
+struct Background(Arc<AtomicBool>);
+
+#[async_trait]
+impl Action for Background {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        let stopped = self.0.clone();
+
+        runtime.add_finalizer("Stop", move || async move {
+            stopped.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        "Background".to_string()
+    }
+}
+
+#[test]
+fn rejects_finalizers_and_runs_them() {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let node: Node = Background(stopped.clone()).into();
+
+    let err = blocking::RuntimeBuilder::new()
+        .add_action(to_blocking(&node))
+        .build()
+        .perform()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::OperationNotSupported);
+    assert!(stopped.load(Ordering::SeqCst), "the finalizer did not run");
+}
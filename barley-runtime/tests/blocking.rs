@@ -0,0 +1,118 @@
+use std::sync::{Arc, Mutex};
+
+use barley_runtime::blocking::{Action, Node, Runtime, RuntimeBuilder};
+use barley_runtime::{Error, Operation, Output, Probe};
+
+
+/// Appends its name to a shared log when it runs.
+struct Step {
+    name: &'static str,
+    log: Arc<Mutex<Vec<&'static str>>>
+}
+
+impl Action for Step {
+    fn run(&self, _runtime: &Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        self.log.lock().unwrap().push(self.name);
+        Ok(None)
+    }
+
+    fn probe(&self, _runtime: &Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    fn display_name(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+/// Build A -> B -> C, added in reverse, plus two
+/// independent actions that tie with A.
+fn chain(log: &Arc<Mutex<Vec<&'static str>>>) -> RuntimeBuilder {
+    let step = |name| Node::from(Step { name, log: log.clone() });
+
+    let a = step("A");
+    let mut b = step("B");
+    let mut c = step("C");
+
+    b.requires(&a);
+    c.requires(&b);
+
+    RuntimeBuilder::new()
+        .add_action(c)
+        .add_action(step("D"))
+        .add_action(b)
+        .add_action(a)
+        .add_action(step("E"))
+}
+
+fn position(log: &[&str], name: &str) -> usize {
+    log.iter().position(|entry| *entry == name).unwrap()
+}
+
+#[test]
+fn runs_dependencies_first() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    chain(&log).build().perform().unwrap();
+
+    // With one worker, ties run in the order the
+    // actions were added.
+    assert_eq!(*log.lock().unwrap(), ["D", "A", "B", "C", "E"]);
+}
+
+#[test]
+fn runs_dependencies_first_on_workers() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    chain(&log).workers(4).build().perform().unwrap();
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.len(), 5);
+    assert!(position(&log, "A") < position(&log, "B"));
+    assert!(position(&log, "B") < position(&log, "C"));
+}
+
+/// Panics when it runs.
+struct Panic;
+
+impl Action for Panic {
+    fn run(&self, _runtime: &Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        panic!("action panicked")
+    }
+
+    fn probe(&self, _runtime: &Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    fn display_name(&self) -> String {
+        "Panic".to_string()
+    }
+}
+
+#[test]
+fn panics_on_workers_stop_the_run() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let panic = Node::from(Panic);
+    let mut dependent = Node::from(Step { name: "dependent", log: log.clone() });
+    dependent.requires(&panic);
+
+    let runtime = RuntimeBuilder::new()
+        .add_action(dependent)
+        .add_action(panic)
+        .workers(2)
+        .build();
+
+    // The panic is passed on instead of leaving the
+    // other worker waiting for the dependency.
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| runtime.perform()));
+
+    assert!(result.is_err());
+    assert!(log.lock().unwrap().is_empty());
+}
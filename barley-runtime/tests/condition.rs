@@ -0,0 +1,88 @@
+use barley_runtime::condition::{IfOutput, IfVariable, Not};
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{Event, Node, RuntimeBuilder, SkipReason};
+
+
+#[tokio::test]
+async fn variables_decide_whether_actions_run() {
+    let recorder = Recorder::new();
+
+    let mut staging: Node = FakeAction::new("Staging").recorder(&recorder).into();
+    staging.run_if(IfVariable::equals("environment", "staging".to_string()));
+
+    let mut production: Node = FakeAction::new("Production").recorder(&recorder).into();
+    production.run_if(IfVariable::equals("environment", "production".to_string()));
+
+    let mut builder = RuntimeBuilder::new()
+        .add_action(staging)
+        .await
+        .add_action(production)
+        .await;
+
+    builder.set_variable("environment", "staging".to_string());
+
+    let runtime = builder.build();
+    let mut events = runtime.subscribe();
+    runtime.perform().await.unwrap();
+
+    recorder.assert_order(&["Staging"]);
+
+    let mut skipped = Vec::new();
+
+    while let Ok(event) = events.try_recv() {
+        if let Event::ActionSkipped { name, reason, .. } = event {
+            skipped.push((name, reason));
+        }
+    }
+
+    assert_eq!(skipped, [("Production".to_string(), SkipReason::ConditionNotMet)]);
+}
+
+#[tokio::test]
+async fn output_conditions_wait_for_their_node() {
+    let recorder = Recorder::new();
+
+    let check: Node = FakeAction::new("Check")
+        .returns(true)
+        .delay(std::time::Duration::from_millis(20))
+        .recorder(&recorder)
+        .into();
+
+    let mut then: Node = FakeAction::new("Then").recorder(&recorder).into();
+    then.run_if(IfOutput::is_true(check.clone()));
+
+    let mut otherwise: Node = FakeAction::new("Otherwise").recorder(&recorder).into();
+    otherwise.run_if(Not::new(IfOutput::is_true(check.clone())));
+
+    RuntimeBuilder::new()
+        .add_action(then)
+        .await
+        .add_action(otherwise)
+        .await
+        .add_action(check)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    recorder.assert_order(&["Check", "Then"]);
+}
+
+#[tokio::test]
+async fn missing_variables_are_not_met() {
+    let recorder = Recorder::new();
+
+    let mut action: Node = FakeAction::new("Action").recorder(&recorder).into();
+    action.run_if(IfVariable::is_true("enabled"));
+
+    RuntimeBuilder::new()
+        .add_action(action)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    assert!(recorder.order().is_empty());
+}
@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use barley_runtime::{
+    Action, Error, ErrorKind, Node, Operation,
+    Output, Probe, Runtime, RuntimeBuilder
+};
+
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Registers a finalizer, then fails if asked to.
+struct Register {
+    name: &'static str,
+    log: Log,
+    fail: bool,
+    finalizer_fails: bool
+}
+
+impl Register {
+    fn new(name: &'static str, log: &Log) -> Self {
+        Self {
+            name,
+            log: log.clone(),
+            fail: false,
+            finalizer_fails: false
+        }
+    }
+}
+
+#[async_trait]
+impl Action for Register {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        let log = self.log.clone();
+        let name = self.name;
+        let fails = self.finalizer_fails;
+
+        runtime.add_finalizer(format!("Clean up {name}"), move || async move {
+            log.lock().unwrap().push(name.to_string());
+
+            if fails {
+                return Err(Error::new(ErrorKind::ActionFailed, "finalizer failed"))
+            }
+
+            Ok(())
+        });
+
+        if self.fail {
+            return Err(Error::new(ErrorKind::CommandFailed, "action failed"))
+        }
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+async fn perform(first: Register, second: Register) -> Result<(), Error> {
+    let first: Node = first.into();
+    let mut second: Node = second.into();
+    second.requires(first.clone());
+
+    RuntimeBuilder::new()
+        .add_action(first)
+        .await
+        .add_action(second)
+        .await
+        .build()
+        .perform()
+        .await
+}
+
+#[tokio::test]
+async fn finalizers_run_in_reverse_order() {
+    let log = Log::default();
+
+    perform(Register::new("A", &log), Register::new("B", &log)).await.unwrap();
+
+    assert_eq!(*log.lock().unwrap(), ["B", "A"]);
+}
+
+#[tokio::test]
+async fn finalizers_run_after_a_failure() {
+    let log = Log::default();
+
+    let err = perform(
+        Register::new("A", &log),
+        Register { fail: true, ..Register::new("B", &log) }
+    ).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::CommandFailed);
+    assert_eq!(*log.lock().unwrap(), ["B", "A"]);
+}
+
+#[tokio::test]
+async fn failed_finalizers_fail_the_run() {
+    let log = Log::default();
+
+    let err = perform(
+        Register { finalizer_fails: true, ..Register::new("A", &log) },
+        Register::new("B", &log)
+    ).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+    assert_eq!(*log.lock().unwrap(), ["B", "A"]);
+}
+
+#[tokio::test]
+async fn failed_finalizers_keep_the_first_error() {
+    let log = Log::default();
+
+    let err = perform(
+        Register { finalizer_fails: true, ..Register::new("A", &log) },
+        Register { fail: true, ..Register::new("B", &log) }
+    ).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::CommandFailed);
+}
@@ -0,0 +1,70 @@
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{
+    Error, ErrorKind, Event, FailurePolicy,
+    Output, RuntimeBuilder, Scope
+};
+
+
+fn hosts() -> Output {
+    Output::List(vec!["host-a".into(), "host-b".into()])
+}
+
+#[tokio::test]
+async fn dynamic_actions_inherit_their_scope() {
+    let recorder = Recorder::new();
+
+    let mut scope = Scope::named("deploy");
+    scope.settings_mut().tags.push("deploy".to_string());
+    scope.settings_mut().retries = Some(1);
+
+    let hosts = scope.add_action(FakeAction::new("Hosts").returns(hosts()));
+
+    let matrix_recorder = recorder.clone();
+    scope.matrix(move |_| {
+        FakeAction::new("Upload")
+            .run_once(Err(Error::new(ErrorKind::ActionFailed, "flaky")))
+            .recorder(&matrix_recorder)
+    })
+        .axis_from(hosts)
+        .build();
+
+    let mut builder = RuntimeBuilder::new().add_scope(scope).await;
+    builder.only_tags(["deploy"]);
+
+    let runtime = builder.build();
+    let mut events = runtime.subscribe();
+    runtime.perform().await.unwrap();
+
+    // Each action failed once and was retried.
+    assert_eq!(recorder.order(), ["Upload"; 4]);
+
+    let mut added = Vec::new();
+
+    while let Ok(event) = events.try_recv() {
+        if let Event::ActionAdded { name, .. } = event {
+            added.push(name);
+        }
+    }
+
+    assert_eq!(added, ["deploy/Upload [host-a]", "deploy/Upload [host-b]"]);
+}
+
+#[tokio::test]
+async fn dynamic_actions_inherit_the_failure_policy() {
+    let mut scope = Scope::new();
+    scope.settings_mut().failure_policy = Some(FailurePolicy::Ignore);
+
+    let hosts = scope.add_action(FakeAction::new("Hosts").returns(hosts()));
+
+    scope.matrix(|_| FakeAction::new("Upload").fails(Error::new(ErrorKind::ActionFailed, "broken")))
+        .axis_from(hosts)
+        .build();
+
+    RuntimeBuilder::new()
+        .add_scope(scope)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+}
@@ -0,0 +1,79 @@
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{Instance, Module, Node, Output, RuntimeBuilder, Scope};
+
+
+struct ServiceParams {
+    name: &'static str,
+    version: &'static str
+}
+
+/// Installs a service, and exports the installed
+/// version.
+struct Service {
+    recorder: Recorder
+}
+
+impl Module for Service {
+    type Params = ServiceParams;
+
+    fn build(&self, params: ServiceParams) -> Instance {
+        let mut scope = Scope::named(params.name);
+
+        let install = scope.add_action(
+            FakeAction::new(format!("Install {}", params.name))
+                .returns(params.version)
+                .recorder(&self.recorder)
+        );
+
+        Instance::new(scope).export("version", install)
+    }
+}
+
+#[tokio::test]
+async fn parameters_reach_each_instance() {
+    let recorder = Recorder::new();
+    let service = Service { recorder: recorder.clone() };
+
+    let mut scope = Scope::new();
+    let web = scope.add_module(&service, ServiceParams { name: "web", version: "1.0" });
+    let worker = scope.add_module(&service, ServiceParams { name: "worker", version: "2.0" });
+
+    assert_eq!(web.names().collect::<Vec<_>>(), ["version"]);
+    assert!(web.get("missing").is_none());
+
+    let runtime = RuntimeBuilder::new().add_scope(scope).await.build();
+    runtime.clone().perform().await.unwrap();
+
+    let mut order = recorder.order();
+    order.sort();
+    assert_eq!(order, ["Install web", "Install worker"]);
+
+    let web = runtime.get_output(web.get("version").unwrap()).await;
+    let worker = runtime.get_output(worker.get("version").unwrap()).await;
+
+    assert!(matches!(web, Some(Output::String(version)) if version == "1.0"));
+    assert!(matches!(worker, Some(Output::String(version)) if version == "2.0"));
+}
+
+#[tokio::test]
+async fn actions_outside_can_depend_on_a_module() {
+    let recorder = Recorder::new();
+    let service = Service { recorder: recorder.clone() };
+
+    let mut scope = Scope::new();
+    let web = scope.add_module(&service, ServiceParams { name: "web", version: "1.0" });
+
+    let mut after: Node = FakeAction::new("After").recorder(&recorder).into();
+    after.requires(web.as_node());
+    scope.add_action(after);
+
+    RuntimeBuilder::new()
+        .add_scope(scope)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    recorder.assert_order(&["Install web", "After"]);
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use barley_runtime::progress::Progress;
+use barley_runtime::testing::FakeAction;
+use barley_runtime::{Error, ErrorKind, Node, RuntimeBuilder};
+
+
+/// A writer that can be read after the view has
+/// finished with it.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn render(nodes: Vec<Node>, size: Option<(usize, usize)>) -> String {
+    let mut builder = RuntimeBuilder::new();
+
+    for node in nodes {
+        builder = builder.add_action(node).await;
+    }
+
+    let runtime = builder.build();
+    let buffer = Buffer::default();
+    let progress = Progress::spawn_to(&runtime, buffer.clone(), size);
+
+    let _ = runtime.perform().await;
+    progress.finish().await;
+
+    buffer.text()
+}
+
+#[tokio::test]
+async fn plain_output_has_a_line_per_event() {
+    let output = render(vec![
+        FakeAction::new("Install").into(),
+        FakeAction::new("Broken").fails(Error::new(ErrorKind::ActionFailed, "broken")).into()
+    ], None).await;
+
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert!(lines.contains(&"[start] Install"));
+    assert!(lines.iter().any(|line| line.starts_with("[done] Install (")));
+    assert!(lines.iter().any(|line| line.starts_with("[fail] Broken (") && line.contains("): ")));
+    assert!(lines.last().unwrap().starts_with("[run] failed ("));
+}
+
+/// Split live output into frames, and return how
+/// far each frame moved the cursor up and how many
+/// lines it drew.
+fn frames(output: &str) -> Vec<(usize, usize)> {
+    output
+        .split("\x1b[J")
+        .filter(|frame| !frame.is_empty())
+        .map(|frame| {
+            let up = frame
+                .strip_prefix("\x1b[")
+                .and_then(|rest| rest.split_once('A'))
+                .and_then(|(count, _)| count.parse().ok())
+                .unwrap_or(0);
+
+            (up, frame.matches('\n').count())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn live_output_fits_the_terminal() {
+    let nodes = (0..20)
+        .map(|index| FakeAction::new(format!("Action {index}")).delay(Duration::from_millis(300)).into())
+        .collect();
+
+    let output = render(nodes, Some((40, 6))).await;
+    let frames = frames(&output);
+
+    assert!(frames.len() > 1);
+    assert!(output.contains("… 16 more lines"));
+
+    for (index, (up, lines)) in frames.iter().enumerate() {
+        assert!(*lines <= 5, "frame {index} drew {lines} lines");
+
+        // Each frame moves back over the one before it.
+        let previous = if index == 0 { 0 } else { frames[index - 1].1 };
+        assert_eq!(*up, previous);
+    }
+
+    // Once everything is done, it is folded into one
+    // line.
+    let last = output.rsplit("\x1b[2K").next().unwrap();
+    assert!(last.starts_with("✔ 20 actions done"), "unexpected last line: {last}");
+}
+
+#[tokio::test]
+async fn long_lines_are_cut_to_the_width() {
+    let output = render(vec![FakeAction::new("x".repeat(100)).into()], Some((20, 10))).await;
+
+    for line in output.split("\x1b[2K").skip(1) {
+        let line = line.split('\n').next().unwrap();
+        assert!(line.chars().count() <= 20, "line too long: {line}");
+    }
+
+    assert!(output.contains('…'));
+}
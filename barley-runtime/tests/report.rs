@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use barley_runtime::report::{Report, Status};
+use barley_runtime::{Error, ErrorKind, Event, Id, SkipReason};
+
+
+const NAME: &str = r#"copy <a> & "b" to 'c'"#;
+const ESCAPED: &str = "copy &lt;a&gt; &amp; &quot;b&quot; to &apos;c&apos;";
+
+/// Build a report with one action in each of the
+/// final states.
+fn report() -> Report {
+    let ids = [Id::default(), Id::default(), Id::default(), Id::default(), Id::default()];
+    let error = Error::new(ErrorKind::ActionFailed, "<failed> & \"stopped\"")
+        .with_detail("it's <broken>")
+        .with_exit_code(2);
+
+    vec![
+        Event::RunStarted {
+            actions: vec![
+                (ids[0], NAME.to_string()),
+                (ids[1], "broken".to_string()),
+                (ids[2], "up to date".to_string()),
+                (ids[3], "cancelled".to_string()),
+                (ids[4], "never run".to_string())
+            ]
+        },
+        Event::ActionStarted { id: ids[0], name: NAME.to_string() },
+        Event::ActionOutput { id: ids[0], line: "<done>".to_string() },
+        Event::ActionFinished { id: ids[0], name: NAME.to_string(), elapsed: Duration::from_millis(1500) },
+        Event::ActionStarted { id: ids[1], name: "broken".to_string() },
+        Event::ActionFailed { id: ids[1], name: "broken".to_string(), elapsed: Duration::from_millis(250), error },
+        Event::ActionSkipped { id: ids[2], name: "up to date".to_string(), reason: SkipReason::UpToDate },
+        Event::ActionStarted { id: ids[3], name: "cancelled".to_string() },
+        Event::RunFinished { success: false, elapsed: Duration::from_secs(2) }
+    ].into_iter().collect()
+}
+
+#[test]
+fn events_set_the_final_status() {
+    let report = report();
+
+    let statuses = report.actions
+        .iter()
+        .map(|action| action.status)
+        .collect::<Vec<_>>();
+
+    assert_eq!(statuses, [
+        Status::Succeeded,
+        Status::Failed,
+        Status::Skipped(SkipReason::UpToDate),
+        Status::Cancelled,
+        Status::NotRun
+    ]);
+    assert!(!report.success);
+    assert_eq!(report.actions[0].output, ["<done>"]);
+}
+
+#[test]
+fn junit_counts_every_status() {
+    let xml = report().to_junit("barley");
+
+    assert!(xml.contains(r#"<testsuites name="barley" tests="5" failures="1" skipped="3" time="2.000">"#));
+    assert!(xml.contains(r#"<testsuite name="barley" tests="5" failures="1" errors="0" skipped="3" time="2.000">"#));
+    assert_eq!(xml.matches("<testcase ").count(), 5);
+    assert_eq!(xml.matches("<failure ").count(), 1);
+    assert!(xml.contains(r#"<skipped message="up_to_date"/>"#));
+    assert!(xml.contains(r#"<skipped message="cancelled"/>"#));
+    assert!(xml.contains(r#"<skipped message="not_run"/>"#));
+}
+
+#[test]
+fn junit_escapes_names_and_errors() {
+    let xml = report().to_junit("<suite>");
+
+    assert!(xml.contains(&format!(r#"<testcase name="{ESCAPED}" classname="&lt;suite&gt;" time="1.500">"#)));
+    assert!(xml.contains(
+        r#"<failure type="ActionFailed" message="&lt;failed&gt; &amp; &quot;stopped&quot;">it&apos;s &lt;broken&gt;</failure>"#
+    ));
+    assert!(xml.contains("<system-out>&lt;done&gt;</system-out>"));
+    assert!(!xml.contains(NAME));
+}
+
+#[test]
+fn junit_drops_control_characters() {
+    let id = Id::default();
+    let report = [
+        Event::RunStarted { actions: vec![(id, "bell\u{7}".to_string())] },
+        Event::ActionOutput { id, line: "a\tb\u{1b}[0m".to_string() }
+    ].into_iter().collect::<Report>();
+
+    let xml = report.to_junit("barley");
+
+    assert!(xml.contains(r#"<testcase name="bell" "#));
+    assert!(xml.contains("<system-out>a\tb[0m</system-out>"));
+}
+
+#[test]
+fn json_keeps_names_and_errors_as_they_are() {
+    let json = report().to_json();
+
+    assert_eq!(json["success"], false);
+    assert_eq!(json["elapsed"], 2.0);
+    assert_eq!(json["actions"].as_array().unwrap().len(), 5);
+
+    let copy = &json["actions"][0];
+    assert_eq!(copy["name"], NAME);
+    assert_eq!(copy["status"], "succeeded");
+    assert_eq!(copy["elapsed"], 1.5);
+    assert!(copy["error"].is_null());
+    assert_eq!(copy["output"][0], "<done>");
+
+    let error = &json["actions"][1]["error"];
+    assert_eq!(json["actions"][1]["status"], "failed");
+    assert_eq!(error["kind"], "ActionFailed");
+    assert_eq!(error["message"], "<failed> & \"stopped\"");
+    assert_eq!(error["detail"], "it's <broken>");
+    assert_eq!(error["exit_code"], 2);
+
+    assert_eq!(json["actions"][2]["status"], "up_to_date");
+    assert!(json["actions"][4]["elapsed"].is_null());
+}
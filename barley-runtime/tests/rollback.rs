@@ -0,0 +1,53 @@
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{Node, RuntimeBuilder};
+
+
+#[tokio::test]
+async fn rollback_runs_in_reverse_dependency_order() {
+    let recorder = Recorder::new();
+
+    let a: Node = FakeAction::new("A").recorder(&recorder).into();
+    let mut b: Node = FakeAction::new("B").recorder(&recorder).into();
+    let mut c: Node = FakeAction::new("C").recorder(&recorder).into();
+    let mut d: Node = FakeAction::new("D").recorder(&recorder).into();
+
+    b.requires(a.clone());
+    c.requires(b.clone());
+    d.requires(a.clone());
+
+    let runtime = RuntimeBuilder::new()
+        .add_action(c)
+        .await
+        .add_action(a)
+        .await
+        .add_action(d)
+        .await
+        .add_action(b)
+        .await
+        .build();
+
+    runtime.clone().perform().await.unwrap();
+    runtime.rollback().await.unwrap();
+
+    recorder.assert_rollback_order(&["C", "B", "D", "A"]);
+}
+
+#[tokio::test]
+async fn rollback_needs_every_action_to_support_it() {
+    let recorder = Recorder::new();
+
+    let a: Node = FakeAction::new("A").recorder(&recorder).into();
+    let b: Node = FakeAction::new("B").no_rollback().recorder(&recorder).into();
+
+    let runtime = RuntimeBuilder::new()
+        .add_action(a)
+        .await
+        .add_action(b)
+        .await
+        .build();
+
+    runtime.clone().perform().await.unwrap();
+
+    assert!(runtime.rollback().await.is_err());
+    assert!(recorder.rollback_order().is_empty());
+}
@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{
+    Action, Error, ErrorKind, Event, FailurePolicy, Node,
+    Operation, Output, Probe, Runtime, RuntimeBuilder,
+    SkipReason
+};
+
+
+const STEP: Duration = Duration::from_millis(50);
+
+async fn perform(nodes: Vec<Node>) -> Result<(), Error> {
+    let mut builder = RuntimeBuilder::new();
+
+    for node in nodes {
+        builder = builder.add_action(node).await;
+    }
+
+    builder.build().perform().await
+}
+
+#[tokio::test]
+async fn dependencies_run_first() {
+    let recorder = Recorder::new();
+
+    let a: Node = FakeAction::new("A").delay(STEP).recorder(&recorder).into();
+    let mut b: Node = FakeAction::new("B").recorder(&recorder).into();
+    let mut c: Node = FakeAction::new("C").recorder(&recorder).into();
+
+    b.requires(a.clone());
+    c.requires(b.clone());
+
+    perform(vec![c, b, a]).await.unwrap();
+
+    recorder.assert_order(&["A", "B", "C"]);
+    recorder.assert_before("A", "B");
+    recorder.assert_before("B", "C");
+}
+
+#[tokio::test]
+async fn independent_actions_run_concurrently() {
+    let recorder = Recorder::new();
+
+    let a: Node = FakeAction::new("A").delay(STEP).recorder(&recorder).into();
+    let b: Node = FakeAction::new("B").delay(STEP).recorder(&recorder).into();
+
+    perform(vec![a, b]).await.unwrap();
+
+    recorder.assert_concurrent("A", "B");
+    assert_eq!(recorder.max_concurrency(), 2);
+}
+
+#[tokio::test]
+async fn up_to_date_actions_are_skipped() {
+    let recorder = Recorder::new();
+
+    let a: Node = FakeAction::new("A").up_to_date().recorder(&recorder).into();
+    let mut b: Node = FakeAction::new("B").recorder(&recorder).into();
+    b.requires(a.clone());
+
+    perform(vec![a, b]).await.unwrap();
+
+    recorder.assert_order(&["B"]);
+}
+
+#[tokio::test]
+async fn cycles_are_reported() {
+    let mut a: Node = FakeAction::new("A").into();
+    let mut b: Node = FakeAction::new("B").into();
+
+    b.requires(a.clone());
+    a.requires(b.clone());
+
+    let err = perform(vec![a, b]).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DependencyCycle);
+}
+
+/// Adds a child action to the running workflow,
+/// then fails if it has an error.
+struct Parent {
+    child: Node,
+    error: Option<Error>
+}
+
+#[async_trait]
+impl Action for Parent {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        runtime.add_action(self.child.clone());
+
+        match &self.error {
+            Some(err) => Err(err.clone()),
+            None => Ok(None)
+        }
+    }
+
+    fn display_name(&self) -> String {
+        "Parent".to_string()
+    }
+}
+
+#[tokio::test]
+async fn added_actions_run_before_dependents_of_their_parent() {
+    let recorder = Recorder::new();
+
+    let child: Node = FakeAction::new("Child").delay(STEP).recorder(&recorder).into();
+    let parent: Node = Parent { child, error: None }.into();
+
+    let mut after: Node = FakeAction::new("After").recorder(&recorder).into();
+    after.requires(parent.clone());
+
+    perform(vec![parent, after]).await.unwrap();
+
+    recorder.assert_order(&["Child", "After"]);
+    recorder.assert_before("Child", "After");
+}
+
+#[tokio::test]
+async fn failures_stop_the_workflow() {
+    let recorder = Recorder::new();
+
+    let a: Node = FakeAction::new("A")
+        .fails(Error::new(ErrorKind::ActionFailed, "broken"))
+        .recorder(&recorder)
+        .into();
+    let mut b: Node = FakeAction::new("B").recorder(&recorder).into();
+    b.requires(a.clone());
+
+    let err = perform(vec![a, b]).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+    recorder.assert_order(&["A"]);
+}
+
+#[tokio::test]
+async fn actions_added_by_a_failed_action_are_skipped() {
+    let recorder = Recorder::new();
+
+    let child: Node = FakeAction::new("Child").recorder(&recorder).into();
+    let mut parent: Node = Parent {
+        child,
+        error: Some(Error::new(ErrorKind::ActionFailed, "broken"))
+    }.into();
+    parent.settings_mut().failure_policy = Some(FailurePolicy::Continue);
+
+    let runtime = RuntimeBuilder::new().add_action(parent).await.build();
+    let mut events = runtime.subscribe();
+    let err = runtime.perform().await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+    assert!(recorder.order().is_empty());
+
+    let mut skipped = false;
+
+    while let Ok(event) = events.try_recv() {
+        if let Event::ActionSkipped { name, reason: SkipReason::DependencyFailed, .. } = event {
+            skipped |= name == "Child";
+        }
+    }
+
+    assert!(skipped, "Child was not reported as skipped");
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{
+    Error, ErrorKind, Event, FailurePolicy,
+    Node, RuntimeBuilder, Scope, SkipReason
+};
+
+
+fn collect(events: &mut tokio::sync::mpsc::UnboundedReceiver<Event>) -> Vec<Event> {
+    let mut collected = Vec::new();
+
+    while let Ok(event) = events.try_recv() {
+        collected.push(event);
+    }
+
+    collected
+}
+
+#[tokio::test]
+async fn scope_nodes_are_not_reported() {
+    let recorder = Recorder::new();
+
+    let mut inner = Scope::named("inner");
+    inner.add_action(FakeAction::new("Install").recorder(&recorder));
+
+    let mut outer = Scope::named("outer");
+    let inner = outer.add_scope(inner);
+
+    let mut after: Node = FakeAction::new("After").recorder(&recorder).into();
+    after.requires(inner);
+    outer.add_action(after);
+
+    let runtime = RuntimeBuilder::new().add_scope(outer).await.build();
+    let mut events = runtime.subscribe();
+    runtime.perform().await.unwrap();
+
+    recorder.assert_order(&["Install", "After"]);
+
+    for event in collect(&mut events) {
+        match event {
+            Event::RunStarted { actions } => {
+                let names = actions.into_iter().map(|(_, name)| name).collect::<Vec<_>>();
+                assert_eq!(names, ["outer/After", "outer/inner/Install"]);
+            },
+            Event::ActionStarted { name, .. } | Event::ActionFinished { name, .. } => {
+                assert!(name.ends_with("Install") || name.ends_with("After"), "unexpected event for {name}");
+            },
+            _ => {}
+        }
+    }
+}
+
+#[tokio::test]
+async fn only_tagged_actions_run() {
+    let recorder = Recorder::new();
+
+    let mut scope = Scope::new();
+    scope.settings_mut().tags.push("deploy".to_string());
+    scope.add_action(FakeAction::new("Upload").recorder(&recorder));
+
+    let mut test: Node = FakeAction::new("Test").recorder(&recorder).into();
+    test.settings_mut().tags.push("test".to_string());
+
+    let untagged: Node = FakeAction::new("Untagged").recorder(&recorder).into();
+
+    let mut builder = RuntimeBuilder::new()
+        .add_scope(scope)
+        .await
+        .add_action(test)
+        .await
+        .add_action(untagged)
+        .await;
+
+    builder.only_tags(["deploy"]);
+
+    let runtime = builder.build();
+    let mut events = runtime.subscribe();
+    runtime.perform().await.unwrap();
+
+    recorder.assert_order(&["Upload"]);
+
+    let skipped = collect(&mut events)
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::ActionSkipped { name, reason: SkipReason::NotTagged, .. } => Some(name),
+            _ => None
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(skipped.len(), 2);
+    assert!(skipped.contains(&"Test".to_string()));
+    assert!(skipped.contains(&"Untagged".to_string()));
+}
+
+fn broken() -> Error {
+    Error::new(ErrorKind::ActionFailed, "broken")
+}
+
+#[tokio::test]
+async fn actions_inherit_retries_from_their_scope() {
+    let recorder = Recorder::new();
+
+    let mut scope = Scope::new();
+    scope.settings_mut().retries = Some(2);
+    scope.add_action(
+        FakeAction::new("Flaky")
+            .run_once(Err(broken()))
+            .run_once(Err(broken()))
+            .recorder(&recorder)
+    );
+
+    RuntimeBuilder::new()
+        .add_scope(scope)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    assert_eq!(recorder.order(), ["Flaky", "Flaky", "Flaky"]);
+}
+
+#[tokio::test]
+async fn node_settings_override_their_scope() {
+    let mut scope = Scope::new();
+    scope.settings_mut().timeout = Some(Duration::from_secs(10));
+
+    let mut slow: Node = FakeAction::new("Slow").delay(Duration::from_millis(200)).into();
+    slow.settings_mut().timeout = Some(Duration::from_millis(20));
+    scope.add_action(slow);
+
+    let err = RuntimeBuilder::new()
+        .add_scope(scope)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+#[tokio::test]
+async fn continue_skips_dependents_and_fails_at_the_end() {
+    let recorder = Recorder::new();
+
+    let mut scope = Scope::new();
+    scope.settings_mut().failure_policy = Some(FailurePolicy::Continue);
+
+    let failing = scope.add_action(FakeAction::new("Failing").fails(broken()).recorder(&recorder));
+
+    let mut dependent: Node = FakeAction::new("Dependent").recorder(&recorder).into();
+    dependent.requires(failing);
+    scope.add_action(dependent);
+
+    scope.add_action(FakeAction::new("Independent").delay(Duration::from_millis(20)).recorder(&recorder));
+
+    let runtime = RuntimeBuilder::new().add_scope(scope).await.build();
+    let mut events = runtime.subscribe();
+    let err = runtime.perform().await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+    assert!(recorder.order().contains(&"Independent".to_string()));
+    assert!(!recorder.order().contains(&"Dependent".to_string()));
+
+    let skipped = collect(&mut events).into_iter().any(|event| matches!(
+        event,
+        Event::ActionSkipped { name, reason: SkipReason::DependencyFailed, .. } if name == "Dependent"
+    ));
+
+    assert!(skipped, "Dependent was not reported as skipped");
+}
+
+#[tokio::test]
+async fn ignore_treats_failures_as_success() {
+    let recorder = Recorder::new();
+
+    let mut failing: Node = FakeAction::new("Failing").fails(broken()).recorder(&recorder).into();
+    failing.settings_mut().failure_policy = Some(FailurePolicy::Ignore);
+
+    let mut dependent: Node = FakeAction::new("Dependent").recorder(&recorder).into();
+    dependent.requires(failing.clone());
+
+    RuntimeBuilder::new()
+        .add_action(failing)
+        .await
+        .add_action(dependent)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    recorder.assert_order(&["Failing", "Dependent"]);
+}
+
+#[tokio::test]
+async fn depending_on_a_scope_waits_for_all_of_it() {
+    let recorder = Recorder::new();
+
+    let mut setup = Scope::named("setup");
+    setup.add_action(FakeAction::new("Fast").recorder(&recorder));
+    setup.add_action(FakeAction::new("Slow").delay(Duration::from_millis(50)).recorder(&recorder));
+
+    let mut deploy = Scope::named("deploy");
+    deploy.requires(&setup.as_node());
+    deploy.add_action(FakeAction::new("Upload").recorder(&recorder));
+
+    RuntimeBuilder::new()
+        .add_scope(deploy)
+        .await
+        .add_scope(setup)
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    recorder.assert_before("Fast", "Upload");
+    recorder.assert_before("Slow", "Upload");
+}
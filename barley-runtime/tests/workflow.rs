@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use barley_runtime::testing::{FakeAction, Recorder};
+use barley_runtime::{
+    Action, Error, ErrorKind, Event, Node, Operation,
+    Output, Probe, Runtime, RuntimeBuilder, Scope, SubWorkflow
+};
+
+
+/// Returns the `environment` variable.
+struct Environment;
+
+#[async_trait]
+impl Action for Environment {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, _operation: Operation) -> Result<Option<Output>, Error> {
+        Ok(runtime.get_variable::<String>("environment").map(|value| Output::String((*value).clone())))
+    }
+
+    fn display_name(&self) -> String {
+        "Environment".to_string()
+    }
+}
+
+async fn perform(node: Node, after: Option<Node>) -> Runtime {
+    let mut builder = RuntimeBuilder::new().add_action(node).await;
+
+    if let Some(after) = after {
+        builder = builder.add_action(after).await;
+    }
+
+    builder.set_variable("environment", "staging".to_string());
+
+    let runtime = builder.build();
+    runtime.clone().perform().await.unwrap();
+    runtime
+}
+
+#[tokio::test]
+async fn exported_outputs_are_returned() {
+    let mut scope = Scope::new();
+    let version = scope.add_action(FakeAction::new("Version").returns("1.2.3"));
+    let environment = scope.add_action(Environment);
+    let silent = scope.add_action(FakeAction::new("Silent"));
+
+    let deploy: Node = SubWorkflow::from_scope("Deploy", &scope)
+        .forward_variable("environment")
+        .export("version", version)
+        .export("environment", environment)
+        .export("silent", silent)
+        .into();
+
+    let runtime = perform(deploy.clone(), None).await;
+
+    let Some(Output::Map(outputs)) = runtime.get_output(deploy).await else {
+        panic!("Deploy did not return a map");
+    };
+
+    assert_eq!(outputs.len(), 2);
+    assert!(matches!(&outputs["version"], Output::String(version) if version == "1.2.3"));
+    assert!(matches!(&outputs["environment"], Output::String(environment) if environment == "staging"));
+}
+
+#[tokio::test]
+async fn child_events_are_reported_as_output() {
+    let mut scope = Scope::new();
+    scope.add_action(FakeAction::new("Install"));
+
+    let deploy: Node = SubWorkflow::from_scope("Deploy", &scope).into();
+
+    let runtime = RuntimeBuilder::new().add_action(deploy).await.build();
+    let mut events = runtime.subscribe();
+    runtime.perform().await.unwrap();
+
+    let mut lines = Vec::new();
+    let mut started = Vec::new();
+
+    while let Ok(event) = events.try_recv() {
+        match event {
+            Event::ActionOutput { line, .. } => lines.push(line),
+            Event::ActionStarted { name, .. } => started.push(name),
+            _ => {}
+        }
+    }
+
+    // Only the node itself shows up in the parent.
+    assert_eq!(started, ["Deploy"]);
+    assert_eq!(lines, ["started Install", "finished Install"]);
+}
+
+#[tokio::test]
+async fn rollback_rolls_back_the_child() {
+    let recorder = Recorder::new();
+
+    let mut scope = Scope::new();
+    let first = scope.add_action(FakeAction::new("First").recorder(&recorder));
+    let mut second: Node = FakeAction::new("Second").recorder(&recorder).into();
+    second.requires(first);
+    scope.add_action(second);
+
+    let deploy: Node = SubWorkflow::from_scope("Deploy", &scope).into();
+
+    perform(deploy, None).await.rollback().await.unwrap();
+
+    recorder.assert_rollback_order(&["Second", "First"]);
+}
+
+#[tokio::test]
+async fn rollback_is_refused_if_a_child_cannot_roll_back() {
+    let recorder = Recorder::new();
+
+    let mut scope = Scope::new();
+    scope.add_action(FakeAction::new("Permanent").no_rollback().recorder(&recorder));
+
+    let deploy: Node = SubWorkflow::from_scope("Deploy", &scope).into();
+
+    let mut after: Node = FakeAction::new("After").recorder(&recorder).into();
+    after.requires(deploy.clone());
+
+    let err = perform(deploy, Some(after)).await.rollback().await.unwrap_err();
+
+    // Nothing is rolled back, not even the action
+    // that would have gone first.
+    assert_eq!(err.kind(), ErrorKind::Internal);
+    assert!(recorder.rollback_order().is_empty());
+}
@@ -6,8 +6,8 @@ use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
-    let secs_1: ActionObject = Sleep::new(Duration::from_secs(1)).into();
-    let mut secs_2: ActionObject = Sleep::new(Duration::from_secs(2)).into();
+    let secs_1: Node = Sleep::new(Duration::from_secs(1)).into();
+    let mut secs_2: Node = Sleep::new(Duration::from_secs(2)).into();
 
     secs_2.requires(secs_1.clone());
 
@@ -3,13 +3,13 @@ use barley_std::process::Command;
 
 
 #[tokio::main]
-async fn main() -> Result<(), ActionError> {
-    let apt_update: ActionObject = Command::new(vec![
+async fn main() -> Result<(), Error> {
+    let apt_update: Node = Command::new(vec![
         "apt-get".to_string().into(),
         "update".to_string().into()
     ]).into();
 
-    let mut apt_install: ActionObject = Command::new(vec![
+    let mut apt_install: Node = Command::new(vec![
         "apt-get".to_string().into(),
         "install".to_string().into(),
         "-y".to_string().into(),
@@ -3,10 +3,10 @@ use barley_std::fs::{WriteFile, ReadFile, DeleteFile};
 
 
 #[tokio::main]
-async fn main() -> Result<(), ActionError> {
-    let write: ActionObject = WriteFile::new_static("foo.txt", "Hello, world!").into();
-    let mut read: ActionObject = ReadFile::new("foo.txt").into();
-    let mut delete: ActionObject = DeleteFile::new("foo.txt").into();
+async fn main() -> Result<(), Error> {
+    let write: Node = WriteFile::new_static("foo.txt", "Hello, world!").into();
+    let mut read: Node = ReadFile::new("foo.txt").into();
+    let mut delete: Node = DeleteFile::new("foo.txt").into();
 
     read.requires(write.clone());
     delete.requires(read.clone());
@@ -0,0 +1,302 @@
+use barley_runtime::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+
+/// Information about the machine a workflow
+/// runs on.
+///
+/// Facts are gathered once, by the first
+/// [`GatherFacts`] action added to a runtime,
+/// and stored as state. They are also set as
+/// variables named after their field. Numbers
+/// are all stored as `u64`, so conditions must
+/// compare them with a `u64` too, like
+/// `IfVariable::equals("facts.cpus", 4u64)`.
+///
+/// | Variable                  | Type          |
+/// |---------------------------|---------------|
+/// | `facts.os.id`             | `String`      |
+/// | `facts.os.id_like`        | `Vec<String>` |
+/// | `facts.os.name`           | `String`      |
+/// | `facts.os.pretty_name`    | `String`      |
+/// | `facts.os.version_id`     | `String`      |
+/// | `facts.kernel`            | `String`      |
+/// | `facts.arch`              | `String`      |
+/// | `facts.cpus`              | `u64`         |
+/// | `facts.memory.total`      | `u64`         |
+/// | `facts.memory.available`  | `u64`         |
+/// | `facts.hostname`          | `String`      |
+/// | `facts.interfaces`        | `Vec<String>` |
+/// | `facts.package_manager`   | `String`      |
+///
+/// Strings that could not be read are empty.
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+    pub os: OsFacts,
+    pub kernel: String,
+    pub arch: String,
+    pub cpus: u64,
+    pub memory: MemoryFacts,
+    pub hostname: String,
+    pub interfaces: Vec<Interface>,
+    pub package_manager: Option<PackageManager>
+}
+
+/// The distribution, from `/etc/os-release`.
+#[derive(Debug, Clone, Default)]
+pub struct OsFacts {
+    pub id: String,
+    pub id_like: Vec<String>,
+    pub name: String,
+    pub pretty_name: String,
+    pub version_id: String
+}
+
+/// Memory sizes in bytes, from `/proc/meminfo`.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFacts {
+    pub total: u64,
+    pub available: u64
+}
+
+/// A network interface, from `/sys/class/net`.
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub name: String,
+    pub mac: Option<String>,
+    pub up: bool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Yum,
+    Zypper,
+    Pacman,
+    Apk
+}
+
+impl PackageManager {
+    const ALL: [(Self, &'static str); 6] = [
+        (Self::Apt, "apt-get"),
+        (Self::Dnf, "dnf"),
+        (Self::Yum, "yum"),
+        (Self::Zypper, "zypper"),
+        (Self::Pacman, "pacman"),
+        (Self::Apk, "apk")
+    ];
+
+    /// The name of the package manager's command.
+    pub fn command(&self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(manager, _)| manager == self)
+            .map(|(_, command)| *command)
+            .unwrap_or_default()
+    }
+
+    fn detect() -> Option<Self> {
+        let path = std::env::var_os("PATH")?;
+
+        Self::ALL.iter().find_map(|(manager, command)| {
+            std::env::split_paths(&path)
+                .any(|dir| dir.join(command).is_file())
+                .then_some(*manager)
+        })
+    }
+}
+
+impl Facts {
+    /// Gather facts about the current machine.
+    ///
+    /// Facts that cannot be read are left empty.
+    pub fn gather() -> Self {
+        Self {
+            os: OsFacts::gather(),
+            kernel: read_trimmed("/proc/sys/kernel/osrelease").unwrap_or_default(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism()
+                .map_or(1, |cpus| cpus.get() as u64),
+            memory: MemoryFacts::gather(),
+            hostname: read_trimmed("/proc/sys/kernel/hostname")
+                .or_else(|| read_trimmed("/etc/hostname"))
+                .unwrap_or_default(),
+            interfaces: Interface::gather(),
+            package_manager: PackageManager::detect()
+        }
+    }
+
+    /// Add the facts to a runtime, as state and as
+    /// variables.
+    pub fn register(self, builder: &mut RuntimeBuilder) {
+        builder
+            .set_variable("facts.os.id", self.os.id.clone())
+            .set_variable("facts.os.id_like", self.os.id_like.clone())
+            .set_variable("facts.os.name", self.os.name.clone())
+            .set_variable("facts.os.pretty_name", self.os.pretty_name.clone())
+            .set_variable("facts.os.version_id", self.os.version_id.clone())
+            .set_variable("facts.kernel", self.kernel.clone())
+            .set_variable("facts.arch", self.arch.clone())
+            .set_variable("facts.cpus", self.cpus)
+            .set_variable("facts.memory.total", self.memory.total)
+            .set_variable("facts.memory.available", self.memory.available)
+            .set_variable("facts.hostname", self.hostname.clone())
+            .set_variable(
+                "facts.interfaces",
+                self.interfaces.iter().map(|interface| interface.name.clone()).collect::<Vec<_>>()
+            )
+            .set_variable(
+                "facts.package_manager",
+                self.package_manager.map(|manager| manager.command().to_string()).unwrap_or_default()
+            );
+
+        builder.add_state(self);
+    }
+}
+
+impl OsFacts {
+    fn gather() -> Self {
+        let release = read_trimmed("/etc/os-release")
+            .or_else(|| read_trimmed("/usr/lib/os-release"))
+            .map(|contents| parse_os_release(&contents))
+            .unwrap_or_default();
+
+        let field = |name: &str| release.get(name).cloned().unwrap_or_default();
+
+        Self {
+            id: field("ID"),
+            id_like: field("ID_LIKE")
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            name: field("NAME"),
+            pretty_name: field("PRETTY_NAME"),
+            version_id: field("VERSION_ID")
+        }
+    }
+}
+
+impl MemoryFacts {
+    fn gather() -> Self {
+        let meminfo = read_trimmed("/proc/meminfo").unwrap_or_default();
+        let mut memory = Self::default();
+
+        for line in meminfo.lines() {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            // Sizes are reported in kibibytes.
+            let bytes = value
+                .split_whitespace()
+                .next()
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(0) * 1024;
+
+            match name {
+                "MemTotal" => memory.total = bytes,
+                "MemAvailable" => memory.available = bytes,
+                _ => {}
+            }
+        }
+
+        memory
+    }
+}
+
+impl Interface {
+    fn gather() -> Vec<Self> {
+        let Ok(entries) = fs::read_dir("/sys/class/net") else {
+            return Vec::new()
+        };
+
+        let mut interfaces = entries
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let path = entry.path();
+
+                Self {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    mac: read_trimmed(path.join("address")).filter(|mac| !mac.is_empty()),
+                    up: read_trimmed(path.join("operstate")).as_deref() == Some("up")
+                }
+            })
+            .collect::<Vec<_>>();
+
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+        interfaces
+    }
+}
+
+fn read_trimmed<P: AsRef<Path>>(path: P) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|contents| contents.trim().to_string())
+}
+
+fn parse_os_release(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Gathers [`Facts`] when it is added to a
+/// runtime.
+///
+/// Adding more than one of these is harmless,
+/// since facts are only gathered once. Other
+/// actions can depend on it to make sure facts
+/// are available: if gathering failed, the
+/// action fails when it runs.
+#[derive(Default)]
+pub struct GatherFacts;
+
+impl GatherFacts {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Action for GatherFacts {
+    async fn probe(&self, runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: runtime.get_state::<Facts>().is_none(),
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Perform) && runtime.get_state::<Facts>().is_none() {
+            return Err(Error::new(ErrorKind::StateNotLoaded, "Facts could not be gathered"))
+        }
+
+        Ok(None)
+    }
+
+    async fn load_state(&self, builder: &mut RuntimeBuilder) {
+        if builder.has_state::<Facts>() {
+            return;
+        }
+
+        // Gathering reads files under /proc, /sys and
+        // /etc, which would block the executor.
+        match tokio::task::spawn_blocking(Facts::gather).await {
+            Ok(facts) => facts.register(builder),
+            Err(e) => tracing::warn!("Failed to gather facts: {}", e)
+        }
+    }
+
+    fn display_name(&self) -> String {
+        "Gather facts".to_string()
+    }
+}
@@ -0,0 +1,191 @@
+use barley_runtime::prelude::*;
+use barley_runtime::condition::{IfOutput, Not, Any};
+
+
+struct Branch {
+    condition: Arc<dyn Condition>
+}
+
+#[async_trait]
+impl Action for Branch {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Ok(None)
+        }
+
+        let result = self.condition.evaluate(runtime).await?;
+
+        Ok(Some(Output::Boolean(result)))
+    }
+
+    fn display_name(&self) -> String {
+        "If".to_string()
+    }
+}
+
+/// Runs one of two scopes, depending on a condition.
+///
+/// The condition is evaluated once, by a node
+/// that every action in both scopes depends on.
+/// Actions in the branch that was not taken are
+/// skipped.
+pub struct If {
+    condition: Arc<dyn Condition>,
+    then: Scope,
+    otherwise: Scope
+}
+
+impl If {
+    pub fn new<C: Condition + 'static>(condition: C) -> Self {
+        Self {
+            condition: Arc::new(condition),
+            then: Scope::new(),
+            otherwise: Scope::new()
+        }
+    }
+
+    /// Set the scope to run when the condition is met.
+    pub fn then(mut self, scope: Scope) -> Self {
+        self.then = scope;
+        self
+    }
+
+    /// Set the scope to run when the condition is not met.
+    pub fn otherwise(mut self, scope: Scope) -> Self {
+        self.otherwise = scope;
+        self
+    }
+
+    /// Build a scope containing the branch and
+    /// both of its scopes.
+    pub fn build(self) -> Scope {
+        let mut branch: Node = Branch {
+            condition: self.condition.clone()
+        }.into();
+
+        for dep in self.condition.dependencies() {
+            branch.requires(dep);
+        }
+
+        let mut then = self.then;
+        then.run_if(IfOutput::is_true(branch.clone()));
+
+        let mut otherwise = self.otherwise;
+        otherwise.run_if(IfOutput::equals(branch.clone(), false));
+
+        let mut scope = Scope::new();
+        scope.add_action(branch);
+        scope.add_scope(then);
+        scope.add_scope(otherwise);
+
+        scope
+    }
+}
+
+struct Selector {
+    value: Input<String>
+}
+
+#[async_trait]
+impl Action for Selector {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Ok(None)
+        }
+
+        let value = match &self.value {
+            Input::Static(value) => value.clone(),
+            Input::Dynamic(node) => runtime.get_output(node.clone()).await
+                .ok_or(Error::from(ErrorKind::NoActionReturn))?
+                .try_into()?
+        };
+
+        Ok(Some(Output::String(value)))
+    }
+
+    fn display_name(&self) -> String {
+        match &self.value {
+            Input::Static(value) => format!("Switch on {}", value),
+            Input::Dynamic(node) => format!("Switch on {}", node.display_name())
+        }
+    }
+}
+
+/// Runs one of several scopes, depending on a value.
+///
+/// The value is resolved once, by a node that
+/// every action in every case depends on. Only
+/// the scope of the matching case runs, or the
+/// default scope if no case matches.
+pub struct Switch {
+    value: Input<String>,
+    cases: Vec<(String, Scope)>,
+    default: Scope
+}
+
+impl Switch {
+    pub fn new<I: Into<Input<String>>>(value: I) -> Self {
+        Self {
+            value: value.into(),
+            cases: Vec::new(),
+            default: Scope::new()
+        }
+    }
+
+    /// Add a scope to run when the value matches.
+    pub fn case<S: ToString>(mut self, value: S, scope: Scope) -> Self {
+        self.cases.push((value.to_string(), scope));
+        self
+    }
+
+    /// Set the scope to run when no case matches.
+    pub fn default(mut self, scope: Scope) -> Self {
+        self.default = scope;
+        self
+    }
+
+    /// Build a scope containing the selector and
+    /// every case.
+    pub fn build(self) -> Scope {
+        let dependency = self.value.dynamic();
+
+        let mut selector: Node = Selector {
+            value: self.value
+        }.into();
+
+        if let Some(dependency) = dependency {
+            selector.requires(dependency);
+        }
+
+        let mut scope = Scope::new();
+        scope.add_action(selector.clone());
+
+        let mut any_case = Any::new();
+
+        for (value, mut case) in self.cases {
+            any_case = any_case.or(IfOutput::equals(selector.clone(), value.clone()));
+            case.run_if(IfOutput::equals(selector.clone(), value));
+            scope.add_scope(case);
+        }
+
+        let mut default = self.default;
+        default.run_if(Not::new(any_case));
+        scope.add_scope(default);
+
+        scope
+    }
+}
@@ -1,11 +1,21 @@
 use barley_runtime::prelude::*;
-use tokio::{fs::File, io::AsyncWriteExt};
-use std::path::PathBuf;
+use regex::Regex;
+use std::ffi::CString;
+use std::fs::{self as std_fs, OpenOptions, Permissions};
+use std::io::{self, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 
 pub struct WriteFile {
     path: PathBuf,
-    content: ActionInput<String>
+    content: Input<String>,
+    mode: Option<u32>,
+    user: Option<Owner>,
+    group: Option<Owner>,
+    backup: Mutex<Option<Backup>>
 }
 
 impl WriteFile {
@@ -14,68 +24,112 @@ impl WriteFile {
         P: Into<PathBuf>,
         S: ToString,
     {
-        Self {
-            path: path.into(),
-            content: ActionInput::new_static(content.to_string()),
-        }
+        Self::new(path.into(), Input::new_static(content.to_string()))
     }
 
-    pub fn new_dynamic<P>(path: P, content: ActionObject) -> Self
+    pub fn new_dynamic<P>(path: P, content: Node) -> Self
     where
         P: Into<PathBuf>,
     {
+        Self::new(path.into(), Input::new_dynamic(content))
+    }
+
+    fn new(path: PathBuf, content: Input<String>) -> Self {
         Self {
-            path: path.into(),
-            content: ActionInput::new_dynamic(content)
+            path,
+            content,
+            mode: None,
+            user: None,
+            group: None,
+            backup: Mutex::new(None)
         }
     }
+
+    /// Set the mode of the file, like `0o644`. By
+    /// default, a new file gets the default mode and
+    /// an existing file keeps its mode.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn user<O: Into<Owner>>(mut self, user: O) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn group<O: Into<Owner>>(mut self, group: O) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    async fn content(&self, runtime: &Runtime) -> Result<String, Error> {
+        match self.content {
+            Input::Static(ref s) => Ok(s.clone()),
+            Input::Dynamic(ref obj) => {
+                let output = runtime.get_output(obj.clone()).await
+                    .ok_or(Error::from(ErrorKind::NoActionReturn))?;
+
+                match output {
+                    Output::String(s) => Ok(s),
+                    _ => Err(ErrorKind::WrongOutputType.into())
+                }
+            }
+        }
+    }
+
+    async fn ids(&self) -> Result<(Option<u32>, Option<u32>), Error> {
+        owner_ids(self.user.as_ref(), self.group.as_ref()).await
+    }
 }
 
 #[async_trait]
 impl Action for WriteFile {
-    async fn probe(&self, _runtime: Runtime) -> Result<Probe, ActionError> {
+    async fn probe(&self, runtime: Runtime) -> Result<Probe, Error> {
+        let content = self.content(&runtime).await?;
+        let (uid, gid) = self.ids().await?;
+
+        let needs_run = match (tokio::fs::read(&self.path).await, tokio::fs::metadata(&self.path).await) {
+            (Ok(existing), Ok(metadata)) => existing != content.as_bytes()
+                || self.mode.is_some_and(|mode| mode != metadata.permissions().mode() & 0o7777)
+                || uid.is_some_and(|uid| uid != metadata.uid())
+                || gid.is_some_and(|gid| gid != metadata.gid()),
+            _ => true
+        };
+
         Ok(Probe {
-            needs_run: true,
-            can_rollback: false
+            needs_run,
+            can_rollback: true
         })
     }
 
-    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<ActionOutput>, ActionError> {
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if matches!(op, Operation::Rollback) {
-            return Err(ActionError::OperationNotSupported)
-        }
+            let backup = self.backup.lock().unwrap_or_else(PoisonError::into_inner).take();
 
-        let content = match self.content {
-            ActionInput::Static(ref s) => s.clone(),
-            ActionInput::Dynamic(ref obj) => {
-                let output = runtime.get_output(obj.clone()).await
-                    .ok_or(ActionError::ActionFailed(
-                        "Failed to get output".to_string(),
-                        "Failed to get output".to_string()
-                    ))?;
+            if let Some(backup) = backup {
+                let path = self.path.clone();
 
-                match output {
-                    ActionOutput::String(s) => s,
-                    _ => return Err(ActionError::ActionFailed(
-                        "Output is not a string".to_string(),
-                        "Output is not a string".to_string()
-                    ))
-                }
+                blocking(move || backup.restore(&path)).await
+                    .map_err(|e| io_error("restore file", &self.path, e))?;
             }
-        };
 
-        let mut file = File::create(&self.path).await
-            .map_err(|e| ActionError::ActionFailed(
-                format!("Failed to create file: {}", e),
-                format!("Failed to create file: {}", self.path.display())
-            ))?;
-        
-        file.write_all(content.as_bytes()).await
-            .map_err(|e| ActionError::ActionFailed(
-                format!("Failed to write to file: {}", e),
-                format!("Failed to write to file: {}", self.path.display())
-            ))?;
-        
+            return Ok(None)
+        }
+
+        let content = self.content(&runtime).await?;
+        let (uid, gid) = self.ids().await?;
+        let (path, mode) = (self.path.clone(), self.mode);
+
+        let backup = blocking(move || {
+            let backup = Backup::read(&path)?;
+            write_atomic(&path, content.as_bytes(), mode, uid, gid)?;
+            Ok(backup)
+        }).await
+            .map_err(|e| io_error("write file", &self.path, e))?;
+
+        *self.backup.lock().unwrap_or_else(PoisonError::into_inner) = Some(backup);
+
         Ok(None)
     }
 
@@ -101,25 +155,25 @@ impl ReadFile {
 
 #[async_trait]
 impl Action for ReadFile {
-    async fn probe(&self, _runtime: Runtime) -> Result<Probe, ActionError> {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
         Ok(Probe {
             needs_run: true,
             can_rollback: false
         })
     }
 
-    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<ActionOutput>, ActionError> {
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if matches!(op, Operation::Rollback) {
-            return Err(ActionError::OperationNotSupported)
+            return Err(ErrorKind::OperationNotSupported.into())
         }
 
         let content = tokio::fs::read_to_string(&self.path).await
-            .map_err(|e| ActionError::ActionFailed(
-                format!("Failed to read file: {}", e),
-                format!("Failed to read file: {}", self.path.display())
-            ))?;
+            .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to read file: {}", e))
+                .with_detail(format!("Failed to read file: {}", self.path.display()))
+                .with_source(e)
+            )?;
 
-        Ok(Some(ActionOutput::String(content)))
+        Ok(Some(Output::String(content)))
     }
 
     fn display_name(&self) -> String {
@@ -144,23 +198,23 @@ impl DeleteFile {
 
 #[async_trait]
 impl Action for DeleteFile {
-    async fn probe(&self, _runtime: Runtime) -> Result<Probe, ActionError> {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
         Ok(Probe {
             needs_run: self.path.exists(),
             can_rollback: false
         })
     }
 
-    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<ActionOutput>, ActionError> {
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if matches!(op, Operation::Rollback) {
-            return Err(ActionError::OperationNotSupported)
+            return Err(ErrorKind::OperationNotSupported.into())
         }
 
         tokio::fs::remove_file(&self.path).await
-            .map_err(|e| ActionError::ActionFailed(
-                format!("Failed to delete file: {}", e),
-                format!("Failed to delete file: {}", self.path.display())
-            ))?;
+            .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to delete file: {}", e))
+                .with_detail(format!("Failed to delete file: {}", self.path.display()))
+                .with_source(e)
+            )?;
 
         Ok(None)
     }
@@ -168,4 +222,993 @@ impl Action for DeleteFile {
     fn display_name(&self) -> String {
         format!("Delete file {}", self.path.display())
     }
-}
\ No newline at end of file
+}
+
+pub struct CreateDir {
+    path: PathBuf
+}
+
+impl CreateDir {
+    /// Create a directory and any missing parents.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into()
+        }
+    }
+}
+
+#[async_trait]
+impl Action for CreateDir {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: !tokio::fs::metadata(&self.path).await.is_ok_and(|metadata| metadata.is_dir()),
+            can_rollback: false
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Err(ErrorKind::OperationNotSupported.into())
+        }
+
+        tokio::fs::create_dir_all(&self.path).await
+            .map_err(|e| io_error("create directory", &self.path, e))?;
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Create directory {}", self.path.display())
+    }
+}
+
+pub struct Copy {
+    from: PathBuf,
+    to: PathBuf
+}
+
+impl Copy {
+    /// Copy a file or a directory tree. `to` is the
+    /// path of the copy, not the directory it goes
+    /// in. Symlinks are copied as symlinks.
+    pub fn new<F, T>(from: F, to: T) -> Self
+    where
+        F: Into<PathBuf>,
+        T: Into<PathBuf>,
+    {
+        Self {
+            from: from.into(),
+            to: to.into()
+        }
+    }
+}
+
+#[async_trait]
+impl Action for Copy {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        let (from, to) = (self.from.clone(), self.to.clone());
+        let same = blocking(move || same_tree(&from, &to)).await;
+
+        Ok(Probe {
+            needs_run: !matches!(same, Ok(true)),
+            can_rollback: false
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Err(ErrorKind::OperationNotSupported.into())
+        }
+
+        let (from, to) = (self.from.clone(), self.to.clone());
+
+        blocking(move || copy_tree(&from, &to)).await
+            .map_err(|e| io_error("copy", &self.from, e))?;
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Copy {} to {}", self.from.display(), self.to.display())
+    }
+}
+
+pub struct Move {
+    from: PathBuf,
+    to: PathBuf
+}
+
+impl Move {
+    /// Move a file or a directory tree, copying it
+    /// if it has to cross filesystems.
+    pub fn new<F, T>(from: F, to: T) -> Self
+    where
+        F: Into<PathBuf>,
+        T: Into<PathBuf>,
+    {
+        Self {
+            from: from.into(),
+            to: to.into()
+        }
+    }
+}
+
+#[async_trait]
+impl Action for Move {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        // Once the source is gone and the destination
+        // exists, the move has happened. With neither,
+        // running reports the missing source.
+        let moved = tokio::fs::symlink_metadata(&self.from).await.is_err()
+            && tokio::fs::symlink_metadata(&self.to).await.is_ok();
+
+        Ok(Probe {
+            needs_run: !moved,
+            can_rollback: false
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Err(ErrorKind::OperationNotSupported.into())
+        }
+
+        let (from, to) = (self.from.clone(), self.to.clone());
+
+        blocking(move || match std_fs::rename(&from, &to) {
+            Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+                copy_tree(&from, &to)?;
+                remove_tree(&from)
+            },
+            result => result
+        }).await
+            .map_err(|e| io_error("move", &self.from, e))?;
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Move {} to {}", self.from.display(), self.to.display())
+    }
+}
+
+pub struct Symlink {
+    target: PathBuf,
+    link: PathBuf
+}
+
+impl Symlink {
+    /// Create a symlink at `link` that points to
+    /// `target`. An existing symlink at `link` is
+    /// replaced, but any other file is left alone
+    /// and the action fails.
+    pub fn new<T, L>(target: T, link: L) -> Self
+    where
+        T: Into<PathBuf>,
+        L: Into<PathBuf>,
+    {
+        Self {
+            target: target.into(),
+            link: link.into()
+        }
+    }
+}
+
+#[async_trait]
+impl Action for Symlink {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        let target = tokio::fs::read_link(&self.link).await.ok();
+
+        Ok(Probe {
+            needs_run: target.as_ref() != Some(&self.target),
+            can_rollback: false
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Err(ErrorKind::OperationNotSupported.into())
+        }
+
+        if let Ok(metadata) = tokio::fs::symlink_metadata(&self.link).await {
+            if !metadata.file_type().is_symlink() {
+                return Err(Error::new(ErrorKind::ActionFailed, "Path exists and is not a symlink")
+                    .with_detail(format!("Failed to create symlink: {}", self.link.display()))
+                )
+            }
+
+            tokio::fs::remove_file(&self.link).await
+                .map_err(|e| io_error("remove symlink", &self.link, e))?;
+        }
+
+        tokio::fs::symlink(&self.target, &self.link).await
+            .map_err(|e| io_error("create symlink", &self.link, e))?;
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Symlink {} to {}", self.link.display(), self.target.display())
+    }
+}
+
+pub struct SetPermissions {
+    path: PathBuf,
+    mode: u32
+}
+
+impl SetPermissions {
+    /// Set the mode of a path, like `0o644`.
+    pub fn new<P>(path: P, mode: u32) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into(),
+            mode
+        }
+    }
+}
+
+#[async_trait]
+impl Action for SetPermissions {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        let mode = tokio::fs::metadata(&self.path).await
+            .map(|metadata| metadata.permissions().mode() & 0o7777);
+
+        Ok(Probe {
+            needs_run: mode.ok() != Some(self.mode),
+            can_rollback: false
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Err(ErrorKind::OperationNotSupported.into())
+        }
+
+        tokio::fs::set_permissions(&self.path, Permissions::from_mode(self.mode)).await
+            .map_err(|e| io_error("set permissions", &self.path, e))?;
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Set permissions of {} to {:o}", self.path.display(), self.mode)
+    }
+}
+
+/// A user or group, by ID or by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    Id(u32),
+    Name(String)
+}
+
+impl From<u32> for Owner {
+    fn from(id: u32) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<&str> for Owner {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_string())
+    }
+}
+
+impl From<String> for Owner {
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl Owner {
+    /// Find the ID, looking names up with the
+    /// system's user or group database. This goes
+    /// through NSS, so users from sources like LDAP
+    /// are found too.
+    async fn resolve(&self, kind: &'static str, lookup: fn(&str) -> io::Result<Option<u32>>) -> Result<u32, Error> {
+        let name = match self {
+            Self::Id(id) => return Ok(*id),
+            Self::Name(name) => name.clone()
+        };
+
+        let query = name.clone();
+
+        tokio::task::spawn_blocking(move || lookup(&query)).await
+            .unwrap_or_else(|e| Err(io::Error::other(e)))
+            .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to look up {}: {}", kind, e))
+                .with_detail(format!("Failed to look up {}: {}", kind, name))
+                .with_source(e)
+            )?
+            .ok_or_else(|| Error::new(ErrorKind::ActionFailed, format!("Unknown {}: {}", kind, name))
+                .with_detail(format!("No {} named {}", kind, name))
+            )
+    }
+}
+
+async fn owner_ids(user: Option<&Owner>, group: Option<&Owner>) -> Result<(Option<u32>, Option<u32>), Error> {
+    let uid = match user {
+        Some(user) => Some(user.resolve("user", lookup_user).await?),
+        None => None
+    };
+
+    let gid = match group {
+        Some(group) => Some(group.resolve("group", lookup_group).await?),
+        None => None
+    };
+
+    Ok((uid, gid))
+}
+
+fn lookup_user(name: &str) -> io::Result<Option<u32>> {
+    lookup(name, libc::getpwnam_r, |entry: &libc::passwd| entry.pw_uid)
+}
+
+fn lookup_group(name: &str) -> io::Result<Option<u32>> {
+    lookup(name, libc::getgrnam_r, |entry: &libc::group| entry.gr_gid)
+}
+
+/// The largest buffer to try for one entry, since
+/// a group with many members can be large.
+const MAX_ENTRY_BUFFER: usize = 1 << 20;
+
+type GetEntry<E> = unsafe extern "C" fn(
+    *const libc::c_char,
+    *mut E,
+    *mut libc::c_char,
+    libc::size_t,
+    *mut *mut E
+) -> libc::c_int;
+
+/// Look a name up with `getpwnam_r` or `getgrnam_r`,
+/// growing the buffer until the entry fits.
+fn lookup<E>(name: &str, get: GetEntry<E>, id: fn(&E) -> u32) -> io::Result<Option<u32>> {
+    let name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut buffer = vec![0 as libc::c_char; 1024];
+
+    loop {
+        // SAFETY: `passwd` and `group` are plain C
+        // structs, for which all zeroes is valid.
+        let mut entry: E = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+
+        // SAFETY: the name is a valid C string, and the
+        // buffer length matches the buffer. The entry's
+        // strings point into the buffer, which outlives
+        // the only field read from it.
+        let code = unsafe {
+            get(name.as_ptr(), &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result)
+        };
+
+        match code {
+            0 if result.is_null() => return Ok(None),
+            0 => return Ok(Some(id(&entry))),
+            // These all mean that the name was not found.
+            libc::ENOENT | libc::ESRCH | libc::EBADF | libc::EPERM => return Ok(None),
+            libc::ERANGE if buffer.len() < MAX_ENTRY_BUFFER => buffer.resize(buffer.len() * 2, 0),
+            code => return Err(io::Error::from_raw_os_error(code))
+        }
+    }
+}
+
+pub struct SetOwner {
+    path: PathBuf,
+    user: Option<Owner>,
+    group: Option<Owner>
+}
+
+impl SetOwner {
+    /// Change the owner of a path. Set the user,
+    /// the group, or both.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into(),
+            user: None,
+            group: None
+        }
+    }
+
+    pub fn user<O: Into<Owner>>(mut self, user: O) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn group<O: Into<Owner>>(mut self, group: O) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    async fn ids(&self) -> Result<(Option<u32>, Option<u32>), Error> {
+        owner_ids(self.user.as_ref(), self.group.as_ref()).await
+    }
+}
+
+#[async_trait]
+impl Action for SetOwner {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        let (uid, gid) = self.ids().await?;
+
+        let needs_run = match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => uid.is_some_and(|uid| uid != metadata.uid())
+                || gid.is_some_and(|gid| gid != metadata.gid()),
+            Err(_) => true
+        };
+
+        Ok(Probe {
+            needs_run,
+            can_rollback: false
+        })
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            return Err(ErrorKind::OperationNotSupported.into())
+        }
+
+        let (uid, gid) = self.ids().await?;
+        let path = self.path.clone();
+
+        blocking(move || std::os::unix::fs::chown(&path, uid, gid)).await
+            .map_err(|e| io_error("set owner", &self.path, e))?;
+
+        Ok(None)
+    }
+
+    fn display_name(&self) -> String {
+        format!("Set owner of {}", self.path.display())
+    }
+}
+
+pub struct EnsureLine {
+    path: PathBuf,
+    line: String,
+    present: bool,
+    matching: Option<Regex>,
+    backup: Mutex<Option<Backup>>
+}
+
+impl EnsureLine {
+    /// Make sure a file contains a line, appending
+    /// it if needed. The file is created if it does
+    /// not exist.
+    pub fn present<P, S>(path: P, line: S) -> Self
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        Self {
+            path: path.into(),
+            line: line.into(),
+            present: true,
+            matching: None,
+            backup: Mutex::new(None)
+        }
+    }
+
+    /// Make sure a file does not contain a line.
+    pub fn absent<P, S>(path: P, line: S) -> Self
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        Self {
+            present: false,
+            ..Self::present(path, line)
+        }
+    }
+
+    /// Find the line with a pattern instead of by
+    /// its exact text. When the line should be
+    /// present, the last matching line is replaced.
+    /// If none match, the line is appended unless
+    /// the file already has it. When it should be
+    /// absent, every matching line is removed.
+    pub fn matching(mut self, pattern: Regex) -> Self {
+        self.matching = Some(pattern);
+        self
+    }
+
+    fn edit(&self, current: &str) -> Result<String, Error> {
+        let is_match = |line: &str| match &self.matching {
+            Some(pattern) => pattern.is_match(line),
+            None => line == self.line
+        };
+
+        let mut lines = current.lines().map(str::to_string).collect::<Vec<_>>();
+
+        if !self.present {
+            lines.retain(|line| !is_match(line));
+        } else if let Some(index) = lines.iter().rposition(|line| is_match(line)) {
+            lines[index] = self.line.clone();
+        } else if !lines.contains(&self.line) {
+            lines.push(self.line.clone());
+        }
+
+        Ok(rebuild(current, lines))
+    }
+}
+
+#[async_trait]
+impl Action for EnsureLine {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        probe_edit(&self.path, |current| self.edit(current)).await
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        run_edit(&self.path, &self.backup, op, |current| self.edit(current)).await
+    }
+
+    fn display_name(&self) -> String {
+        if self.present {
+            format!("Ensure line in {}", self.path.display())
+        } else {
+            format!("Remove line from {}", self.path.display())
+        }
+    }
+}
+
+pub struct ReplaceRegex {
+    path: PathBuf,
+    pattern: Regex,
+    replacement: String,
+    backup: Mutex<Option<Backup>>
+}
+
+impl ReplaceRegex {
+    /// Replace every match of a pattern in a file.
+    ///
+    /// The pattern is matched against the whole
+    /// file, so use `(?m)` for `^` and `$` to match
+    /// at line boundaries. The replacement can refer
+    /// to groups, like `$1`. It should not match the
+    /// pattern itself, or the action will never be
+    /// up to date.
+    pub fn new<P, S>(path: P, pattern: Regex, replacement: S) -> Self
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        Self {
+            path: path.into(),
+            pattern,
+            replacement: replacement.into(),
+            backup: Mutex::new(None)
+        }
+    }
+
+    fn edit(&self, current: &str) -> Result<String, Error> {
+        Ok(self.pattern.replace_all(current, self.replacement.as_str()).into_owned())
+    }
+}
+
+#[async_trait]
+impl Action for ReplaceRegex {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        probe_edit(&self.path, |current| self.edit(current)).await
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        run_edit(&self.path, &self.backup, op, |current| self.edit(current)).await
+    }
+
+    fn display_name(&self) -> String {
+        format!("Replace {} in {}", self.pattern, self.path.display())
+    }
+}
+
+pub struct ManagedBlock {
+    path: PathBuf,
+    content: Option<String>,
+    marker: String,
+    backup: Mutex<Option<Backup>>
+}
+
+impl ManagedBlock {
+    /// Keep a block of lines in a file, between
+    /// `# BEGIN barley` and `# END barley` lines.
+    /// The block is appended if the file does not
+    /// have one yet, and replaced if it does. A
+    /// `BEGIN` line without its own `END` line is
+    /// an error, so lines around it are never lost.
+    pub fn new<P, S>(path: P, content: S) -> Self
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        Self {
+            path: path.into(),
+            content: Some(content.into()),
+            marker: "barley".to_string(),
+            backup: Mutex::new(None)
+        }
+    }
+
+    /// Remove the block, along with its marker lines.
+    pub fn absent<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            content: None,
+            ..Self::new(path, String::new())
+        }
+    }
+
+    /// Set the name in the marker lines, so a file
+    /// can hold several blocks.
+    pub fn marker<S: Into<String>>(mut self, marker: S) -> Self {
+        self.marker = marker.into();
+        self
+    }
+
+    fn edit(&self, current: &str) -> Result<String, Error> {
+        let begin = format!("# BEGIN {}", self.marker);
+        let end = format!("# END {}", self.marker);
+
+        let mut lines = current.lines().map(str::to_string).collect::<Vec<_>>();
+
+        let block = self.content.as_ref().map(|content| {
+            std::iter::once(begin.clone())
+                .chain(content.lines().map(str::to_string))
+                .chain(std::iter::once(end.clone()))
+                .collect::<Vec<_>>()
+        });
+
+        let start = lines.iter().position(|line| *line == begin);
+        let stop = start.and_then(|start| {
+            lines[start..].iter().position(|line| *line == end).map(|offset| start + offset)
+        });
+
+        match (start, stop) {
+            (Some(start), Some(stop)) if !lines[start + 1..stop].contains(&begin) => {
+                lines.splice(start..=stop, block.unwrap_or_default());
+            },
+            (Some(_), _) => {
+                return Err(Error::new(ErrorKind::ActionFailed, format!("\"{}\" has no matching \"{}\"", begin, end))
+                    .with_detail(format!("Failed to manage block: {}", self.path.display()))
+                )
+            },
+            (None, _) => lines.extend(block.unwrap_or_default())
+        }
+
+        Ok(rebuild(current, lines))
+    }
+}
+
+#[async_trait]
+impl Action for ManagedBlock {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        probe_edit(&self.path, |current| self.edit(current)).await
+    }
+
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        run_edit(&self.path, &self.backup, op, |current| self.edit(current)).await
+    }
+
+    fn display_name(&self) -> String {
+        format!("Manage block {} in {}", self.marker, self.path.display())
+    }
+}
+
+/// Join edited lines, keeping the line endings and
+/// trailing newline of the original content. New
+/// files get one.
+fn rebuild(current: &str, lines: Vec<String>) -> String {
+    // Files with Windows line endings keep them, as
+    // judged by the first line.
+    let ending = match current.find('\n') {
+        Some(end) if current[..end].ends_with('\r') => "\r\n",
+        _ => "\n"
+    };
+
+    let mut content = lines.join(ending);
+
+    if !content.is_empty() && (current.is_empty() || current.ends_with('\n')) {
+        content.push_str(ending);
+    }
+
+    content
+}
+
+/// Read a file as text. A missing file reads as
+/// empty.
+async fn read_text(path: &Path) -> Result<String, Error> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(io_error("read file", path, e))
+    }
+}
+
+async fn probe_edit<F>(path: &Path, edit: F) -> Result<Probe, Error>
+where
+    F: FnOnce(&str) -> Result<String, Error>,
+{
+    let current = read_text(path).await?;
+
+    Ok(Probe {
+        needs_run: edit(&current)? != current,
+        can_rollback: true
+    })
+}
+
+/// Apply an edit to a file, keeping a backup, or
+/// restore the backup on rollback.
+async fn run_edit<F>(path: &Path, backup: &Mutex<Option<Backup>>, op: Operation, edit: F) -> Result<Option<Output>, Error>
+where
+    F: FnOnce(&str) -> Result<String, Error>,
+{
+    let owned = path.to_path_buf();
+
+    if matches!(op, Operation::Rollback) {
+        let previous = backup.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+        if let Some(previous) = previous {
+            blocking(move || previous.restore(&owned)).await
+                .map_err(|e| io_error("restore file", path, e))?;
+        }
+
+        return Ok(None)
+    }
+
+    let previous = blocking({
+        let path = owned.clone();
+        move || Backup::read(&path)
+    }).await
+        .map_err(|e| io_error("read file", path, e))?;
+
+    let current = previous.text()
+        .map_err(|e| io_error("read file", path, e))?;
+
+    let updated = edit(&current)?;
+
+    if updated == current {
+        return Ok(None)
+    }
+
+    blocking(move || write_atomic(&owned, updated.as_bytes(), None, None, None)).await
+        .map_err(|e| io_error("write file", path, e))?;
+
+    *backup.lock().unwrap_or_else(PoisonError::into_inner) = Some(previous);
+
+    Ok(None)
+}
+
+fn io_error(action: &str, path: &Path, e: io::Error) -> Error {
+    Error::new(ErrorKind::Io, format!("Failed to {}: {}", action, e))
+        .with_detail(format!("Failed to {}: {}", action, path.display()))
+        .with_source(e)
+}
+
+/// Run blocking filesystem work off the async
+/// threads.
+async fn blocking<T, F>(work: F) -> io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work).await
+        .unwrap_or_else(|e| Err(io::Error::other(e)))
+}
+
+/// Check whether `to` is already a copy of `from`.
+fn same_tree(from: &Path, to: &Path) -> io::Result<bool> {
+    let source = std_fs::symlink_metadata(from)?;
+
+    let Ok(target) = std_fs::symlink_metadata(to) else {
+        return Ok(false)
+    };
+
+    if source.file_type().is_symlink() {
+        return Ok(target.file_type().is_symlink() && std_fs::read_link(from)? == std_fs::read_link(to)?)
+    }
+
+    if source.is_dir() {
+        if !target.is_dir() {
+            return Ok(false)
+        }
+
+        for entry in std_fs::read_dir(from)? {
+            let entry = entry?;
+
+            if !same_tree(&entry.path(), &to.join(entry.file_name()))? {
+                return Ok(false)
+            }
+        }
+
+        return Ok(true)
+    }
+
+    Ok(target.is_file() && source.len() == target.len() && std_fs::read(from)? == std_fs::read(to)?)
+}
+
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    let source = std_fs::symlink_metadata(from)?;
+
+    if source.file_type().is_symlink() {
+        if std_fs::symlink_metadata(to).is_ok() {
+            remove_tree(to)?;
+        }
+
+        return std::os::unix::fs::symlink(std_fs::read_link(from)?, to)
+    }
+
+    if source.is_dir() {
+        std_fs::create_dir_all(to)?;
+
+        for entry in std_fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+
+        return Ok(())
+    }
+
+    std_fs::copy(from, to).map(|_| ())
+}
+
+fn remove_tree(path: &Path) -> io::Result<()> {
+    if std_fs::symlink_metadata(path)?.is_dir() {
+        std_fs::remove_dir_all(path)
+    } else {
+        std_fs::remove_file(path)
+    }
+}
+
+/// The state of a file before an action changed
+/// it, for rollback.
+enum Backup {
+    Missing,
+    Content {
+        bytes: Vec<u8>,
+        mode: u32,
+        uid: u32,
+        gid: u32
+    }
+}
+
+impl Backup {
+    fn read(path: &Path) -> io::Result<Self> {
+        match std_fs::read(path) {
+            Ok(bytes) => {
+                let metadata = std_fs::metadata(path)?;
+
+                Ok(Self::Content {
+                    bytes,
+                    mode: metadata.permissions().mode() & 0o7777,
+                    uid: metadata.uid(),
+                    gid: metadata.gid()
+                })
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::Missing),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn text(&self) -> io::Result<String> {
+        match self {
+            Self::Content { bytes, .. } => String::from_utf8(bytes.clone())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Self::Missing => Ok(String::new())
+        }
+    }
+
+    fn restore(&self, path: &Path) -> io::Result<()> {
+        match self {
+            Self::Content { bytes, mode, uid, gid } => write_atomic(path, bytes, Some(*mode), Some(*uid), Some(*gid)),
+            // Remove the file that was written, which is
+            // not the path itself if that is a symlink.
+            Self::Missing => match std_fs::remove_file(resolve_symlinks(path)?) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(())
+            }
+        }
+    }
+}
+
+/// How many symlinks to follow before giving up,
+/// like the kernel's own limit.
+const MAX_SYMLINKS: usize = 40;
+
+/// Counts temporary files, so that concurrent
+/// writes in this process use different names.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Replace a file by writing a temporary file next
+/// to it and renaming it into place, so readers
+/// never see a partly written file.
+///
+/// If the path is a symlink, the file it points to
+/// is replaced and the link is kept. Without a
+/// mode or owner, an existing file keeps its own.
+fn write_atomic(path: &Path, content: &[u8], mode: Option<u32>, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    let path = resolve_symlinks(path)?;
+    let existing = std_fs::metadata(&path).ok();
+
+    let mode = mode.or_else(|| existing.as_ref().map(|metadata| metadata.permissions().mode() & 0o7777));
+    let uid = uid.or_else(|| existing.as_ref().map(MetadataExt::uid));
+    let gid = gid.or_else(|| existing.as_ref().map(MetadataExt::gid));
+
+    let (mut file, temp) = create_temp(&path)?;
+
+    let result = (|| {
+        file.write_all(content)?;
+        file.sync_all()?;
+
+        // Only change what differs, so that writing a
+        // file we already own needs no privileges.
+        let metadata = file.metadata()?;
+        let uid = uid.filter(|uid| *uid != metadata.uid());
+        let gid = gid.filter(|gid| *gid != metadata.gid());
+
+        if uid.is_some() || gid.is_some() {
+            std::os::unix::fs::fchown(&file, uid, gid)?;
+        }
+
+        // Set the mode last, since changing the owner
+        // clears the setuid and setgid bits.
+        if let Some(mode) = mode {
+            file.set_permissions(Permissions::from_mode(mode))?;
+        }
+
+        std_fs::rename(&temp, &path)
+    })();
+
+    if result.is_err() {
+        let _ = std_fs::remove_file(&temp);
+    }
+
+    result
+}
+
+/// Create a new temporary file next to a path.
+///
+/// The file is created exclusively, so it is never
+/// shared with another write, even from another
+/// process.
+fn create_temp(path: &Path) -> io::Result<(std_fs::File, PathBuf)> {
+    let name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_string_lossy();
+
+    loop {
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp = path.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), count));
+
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((file, temp)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e)
+        }
+    }
+}
+
+/// Follow a path through any symlinks to the file
+/// they point to, which may not exist yet.
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut current = path.to_path_buf();
+
+    for _ in 0..MAX_SYMLINKS {
+        match std_fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let target = std_fs::read_link(&current)?;
+
+                current = match current.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target
+                };
+            },
+            _ => return Ok(current)
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::InvalidInput, format!("too many symlinks: {}", path.display())))
+}
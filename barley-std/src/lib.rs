@@ -7,6 +7,11 @@ pub mod fs;
 #[cfg(feature = "process")]
 pub mod process;
 
+#[cfg(feature = "facts")]
+pub mod facts;
+
+pub mod flow;
+
 use barley_runtime::prelude::*;
 
 #[derive(Default)]
@@ -20,14 +25,14 @@ impl Join {
 
 #[async_trait]
 impl Action for Join {
-    async fn probe(&self, _runtime: Runtime) -> Result<Probe, ActionError> {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
         Ok(Probe {
             needs_run: false,
             can_rollback: false
         })
     }
 
-    async fn run(&self, _runtime: Runtime, _op: Operation) -> Result<Option<ActionOutput>, ActionError> {
+    async fn run(&self, _runtime: Runtime, _op: Operation) -> Result<Option<Output>, Error> {
         Ok(None)
     }
 
@@ -1,40 +1,330 @@
 use barley_runtime::prelude::*;
-use tokio::process::Command as TokioCommand;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, ChildStderr, ChildStdout, Command as TokioCommand};
+use tokio::sync::oneshot;
+
+/// The number of lines of stderr included in the
+/// error of a failed command.
+const STDERR_TAIL_LINES: usize = 20;
+
+/// How often a spawned process is checked for
+/// readiness.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long each TCP readiness check may take, so
+/// that an address that drops connections can't
+/// hold up the readiness timeout.
+const READY_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a spawned process has to exit after
+/// `SIGTERM` before it is killed.
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How the output of a command is returned.
+///
+/// When output is captured, the action returns an
+/// [`Output::Map`] with `stdout`, `stderr` and
+/// `code` entries. Stderr is always read, so
+/// that failures can include its last lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Capture {
+    /// Discard stdout and return nothing.
+    #[default]
+    None,
+    /// Return stdout as a single string.
+    Text,
+    /// Return stdout as a list of lines.
+    Lines
+}
 
 pub struct Command {
-    command: Vec<ActionInput<String>>,
-    check: Option<Vec<ActionInput<String>>>,
-    undo: Option<Vec<ActionInput<String>>>
+    command: Vec<Input<String>>,
+    check: Option<Vec<Input<String>>>,
+    undo: Option<Vec<Input<String>>>,
+    capture: Capture,
+    env: Vec<(String, Input<String>)>,
+    env_clear: bool,
+    cwd: Option<PathBuf>,
+    stdin: Option<Input<String>>,
+    umask: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    interpreter: Option<Vec<String>>,
+    success_codes: Vec<i32>,
+    check_codes: Option<CheckCodes>,
+    creates: Option<PathBuf>,
+    removes: Option<PathBuf>,
+    log_file: Option<PathBuf>
+}
+
+/// How the exit code of a check maps to a probe.
+///
+/// Codes in neither list make the probe fail, so a
+/// check that crashes is not mistaken for one that
+/// found work to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckCodes {
+    pub up_to_date: Vec<i32>,
+    pub needs_run: Vec<i32>
 }
 
 impl Command {
-    pub fn new(command: Vec<ActionInput<String>>) -> Self {
+    pub fn new(command: Vec<Input<String>>) -> Self {
         Self {
             command,
             check: None,
-            undo: None
+            undo: None,
+            capture: Capture::None,
+            env: Vec::new(),
+            env_clear: false,
+            cwd: None,
+            stdin: None,
+            umask: None,
+            uid: None,
+            gid: None,
+            interpreter: None,
+            success_codes: vec![0],
+            check_codes: None,
+            creates: None,
+            removes: None,
+            log_file: None
         }
     }
 
-    pub fn check(&mut self, check: Vec<ActionInput<String>>) -> &mut Self {
+    /// Run a script through a shell.
+    ///
+    /// The script is passed to `sh -c` unless
+    /// another [`interpreter`] is set, so it can use
+    /// pipes, redirections and several lines. In
+    /// shell mode, [`check_shell`] and
+    /// [`undo_shell`] take scripts as well.
+    ///
+    /// [`interpreter`]: #method.interpreter
+    /// [`check_shell`]: #method.check_shell
+    /// [`undo_shell`]: #method.undo_shell
+    pub fn shell<S: Into<Input<String>>>(script: S) -> Self {
+        let mut command = Self::new(vec![script.into()]);
+        command.interpreter = Some(vec!["sh".to_string(), "-c".to_string()]);
+        command
+    }
+
+    /// Set the command that runs shell scripts,
+    /// such as `["bash", "-euo", "pipefail", "-c"]`.
+    /// The script is passed as the last argument.
+    pub fn interpreter<I, S>(&mut self, interpreter: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>
+    {
+        self.interpreter = Some(interpreter.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set the exit codes that count as success.
+    /// Defaults to `0` only.
+    pub fn success_codes<C: Into<Vec<i32>>>(&mut self, codes: C) -> &mut Self {
+        self.success_codes = codes.into();
+        self
+    }
+
+    /// Map the exit codes of the check.
+    ///
+    /// Without this, a check that exits with `0`
+    /// means the command is up to date, and any
+    /// other exit means it needs to run.
+    pub fn check_codes(&mut self, codes: CheckCodes) -> &mut Self {
+        self.check_codes = Some(codes);
+        self
+    }
+
+    /// Skip the command if a path exists.
+    pub fn creates<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.creates = Some(path.into());
+        self
+    }
+
+    /// Skip the command if a path does not exist.
+    pub fn removes<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.removes = Some(path.into());
+        self
+    }
+
+    /// Set the check of a shell command to a script.
+    pub fn check_shell<S: Into<Input<String>>>(&mut self, script: S) -> &mut Self {
+        self.check(vec![script.into()])
+    }
+
+    /// Set the undo of a shell command to a script.
+    pub fn undo_shell<S: Into<Input<String>>>(&mut self, script: S) -> &mut Self {
+        self.undo(vec![script.into()])
+    }
+
+    pub fn check(&mut self, check: Vec<Input<String>>) -> &mut Self {
         self.check = Some(check);
         self
     }
 
-    pub fn undo(&mut self, undo: Vec<ActionInput<String>>) -> &mut Self {
+    pub fn undo(&mut self, undo: Vec<Input<String>>) -> &mut Self {
         self.undo = Some(undo);
         self
     }
+
+    pub fn capture(&mut self, capture: Capture) -> &mut Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Set an environment variable.
+    ///
+    /// Like every option below, this applies to the
+    /// main, check and undo commands alike.
+    pub fn env<K: Into<String>, V: Into<Input<String>>>(&mut self, key: K, value: V) -> &mut Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Start from an empty environment instead of
+    /// inheriting the engine's.
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.env_clear = true;
+        self
+    }
+
+    pub fn cwd<P: Into<PathBuf>>(&mut self, cwd: P) -> &mut Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Write a string to the command's stdin, then
+    /// close it. Without this, stdin is empty.
+    pub fn stdin<I: Into<Input<String>>>(&mut self, stdin: I) -> &mut Self {
+        self.stdin = Some(stdin.into());
+        self
+    }
+
+    pub fn umask(&mut self, umask: u32) -> &mut Self {
+        self.umask = Some(umask);
+        self
+    }
+
+    pub fn uid(&mut self, uid: u32) -> &mut Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    pub fn gid(&mut self, gid: u32) -> &mut Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Append the output of the command to a file,
+    /// as it is streamed.
+    pub fn log_file<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    async fn execute(&self, argv: &Vec<Input<String>>, runtime: &Runtime, stream: bool) -> Result<(String, std::process::Output), Error> {
+        let mut argv = resolve_argv(argv, runtime.clone()).await?;
+
+        if let Some(interpreter) = &self.interpreter {
+            argv.splice(0..0, interpreter.iter().cloned());
+        }
+
+        let Some(name) = argv.first().cloned() else {
+            return Err(Error::new(ErrorKind::ActionFailed, "Empty command"))
+        };
+
+        let mut command = TokioCommand::new(&name);
+        command
+            .args(argv.into_iter().skip(1).collect::<Vec<String>>())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        if self.env_clear {
+            command.env_clear();
+        }
+
+        for (key, value) in &self.env {
+            command.env(key, resolve_input(value, runtime).await?);
+        }
+
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        if let Some(uid) = self.uid {
+            command.uid(uid);
+        }
+
+        if let Some(gid) = self.gid {
+            command.gid(gid);
+        }
+
+        if let Some(umask) = self.umask {
+            // SAFETY: `umask` is async-signal-safe.
+            unsafe {
+                command.pre_exec(move || {
+                    libc::umask(umask as libc::mode_t);
+                    Ok(())
+                });
+            }
+        }
+
+        let stdin = match &self.stdin {
+            Some(stdin) => Some(resolve_input(stdin, runtime).await?),
+            None => None
+        };
+
+        command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+
+        let spawn_error = |e: std::io::Error| Error::new(ErrorKind::Io, format!("Internal spawn error: {}", e))
+            .with_detail(format!("Failed to spawn command: {}. This is a bug in the Barley engine.", name))
+            .with_source(e);
+
+        let mut child = command.spawn().map_err(spawn_error)?;
+
+        if let (Some(stdin), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            // Write from a task, so a command that fills its
+            // stdout before reading all of stdin can't block.
+            tokio::spawn(async move {
+                let _ = pipe.write_all(stdin.as_bytes()).await;
+            });
+        }
+
+        let output = if stream {
+            stream_output(child, runtime, self.log_file.as_deref())
+                .await
+                .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to read output: {}", e))
+                    .with_detail(format!("Failed to read the output of command: {}", name))
+                    .with_source(e)
+                )?
+        } else {
+            child.wait_with_output().await.map_err(spawn_error)?
+        };
+
+        Ok((name, output))
+    }
 }
 
-async fn resolve_argv(argv: &Vec<ActionInput<String>>, ctx: Runtime) -> Result<Vec<String>, ActionError> {
+async fn resolve_argv(argv: &Vec<Input<String>>, ctx: Runtime) -> Result<Vec<String>, Error> {
     let mut resolved = Vec::new();
 
     for arg in argv {
         resolved.push(match arg {
-            ActionInput::Static(value) => value.clone(),
-            ActionInput::Dynamic(output) => ctx.get_output(output.clone()).await
-                .ok_or(ActionError::NoActionReturn)?
+            Input::Static(value) => value.clone(),
+            Input::Dynamic(output) => ctx.get_output(output.clone()).await
+                .ok_or(Error::from(ErrorKind::NoActionReturn))?
                 .try_into()?
         });
     }
@@ -42,26 +332,32 @@ async fn resolve_argv(argv: &Vec<ActionInput<String>>, ctx: Runtime) -> Result<V
     Ok(resolved)
 }
 
+async fn resolve_input(input: &Input<String>, ctx: &Runtime) -> Result<String, Error> {
+    match input {
+        Input::Static(value) => Ok(value.clone()),
+        Input::Dynamic(output) => ctx.get_output(output.clone()).await
+            .ok_or(Error::from(ErrorKind::NoActionReturn))?
+            .try_into()
+    }
+}
+
 #[async_trait]
 impl Action for Command {
-    async fn probe(&self, runtime: Runtime) -> Result<Probe, ActionError> {
+    async fn probe(&self, runtime: Runtime) -> Result<Probe, Error> {
+        let guarded = self.creates.as_ref().is_some_and(|path| path.exists())
+            || self.removes.as_ref().is_some_and(|path| !path.exists());
+
         let needs_run = match &self.check {
+            _ if guarded => false,
             Some(check) => {
-                let argv = resolve_argv(check, runtime).await?;
-                let name = argv.first().unwrap().clone();
-
-                let status = TokioCommand::new(argv.first().unwrap())
-                    .args(&argv.into_iter().skip(1).collect::<Vec<String>>())
-                    .stdout(std::process::Stdio::null())
-                    .stderr(std::process::Stdio::null())
-                    .status()
-                    .await
-                    .map_err(|e| ActionError::ActionFailed(
-                        format!("Internal spawn error: {}", e),
-                        format!("Failed to spawn command: {}. This is a bug in the Barley engine.", name))
-                    )?;
-                
-                !status.success()
+                let (name, output) = self.execute(check, &runtime, false).await?;
+
+                match (&self.check_codes, output.status.code()) {
+                    (None, _) => !output.status.success(),
+                    (Some(codes), Some(code)) if codes.up_to_date.contains(&code) => false,
+                    (Some(codes), Some(code)) if codes.needs_run.contains(&code) => true,
+                    (Some(_), _) => return Err(command_failed("Check", &name, output.status, &output.stderr))
+                }
             },
             None => true
         };
@@ -72,44 +368,481 @@ impl Action for Command {
         })
     }
 
-    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<ActionOutput>, ActionError> {
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if matches!(op, Operation::Rollback) && self.undo.is_none() {
-            return Err(ActionError::OperationNotSupported)
+            return Err(ErrorKind::OperationNotSupported.into())
         }
 
-        let argv = resolve_argv(match op {
+        let argv = match op {
             Operation::Perform => &self.command,
             Operation::Rollback => self.undo.as_ref().unwrap()
-        }, runtime).await?;
-
-        let name = argv.first().unwrap().clone();
-
-        let status = TokioCommand::new(argv.first().unwrap())
-            .args(&argv.into_iter().skip(1).collect::<Vec<String>>())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()
-            .await
-            .map_err(|e| ActionError::ActionFailed(
-                format!("Internal spawn error: {}", e),
-                format!("Failed to spawn command: {}. This is a bug in the Barley engine.", name)
-            ))?;
-        
-        if !status.success() {
-            return Err(ActionError::ActionFailed(
-                format!("Command exited with non-zero status code: {}", status.code().unwrap_or(1)),
-                format!("Failed to run command: {}", name)
-            ))
-        } else {
-            Ok(None)
+        };
+
+        let (name, output) = self.execute(argv, &runtime, true).await?;
+
+        let code = output.status.code();
+
+        if !code.is_some_and(|code| self.success_codes.contains(&code)) {
+            return Err(command_failed("Command", &name, output.status, &output.stderr))
         }
+
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+
+        let stdout = match self.capture {
+            Capture::None => return Ok(None),
+            Capture::Text => Output::String(stdout),
+            Capture::Lines => stdout.lines().map(str::to_string).collect::<Vec<_>>().into()
+        };
+
+        let mut captured = BTreeMap::new();
+        captured.insert("stdout".to_string(), stdout);
+        captured.insert("stderr".to_string(), Output::String(stderr));
+        captured.insert("code".to_string(), Output::Integer(code.unwrap_or(0).into()));
+
+        Ok(Some(Output::Map(captured)))
     }
 
     fn display_name(&self) -> String {
+        if self.interpreter.is_some() {
+            return format!("Shell: {}", match self.command.first() {
+                Some(Input::Static(script)) => script.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim(),
+                Some(Input::Dynamic(_)) => "<dynamic>",
+                None => "<empty>"
+            })
+        }
+
         format!("Command: {}", match self.command.first() {
-            Some(ActionInput::Static(value)) => value,
-            Some(ActionInput::Dynamic(_)) => "<dynamic>",
+            Some(Input::Static(value)) => value,
+            Some(Input::Dynamic(_)) => "<dynamic>",
+            None => "<empty>"
+        })
+    }
+}
+
+/// Wait for a child process, streaming its output
+/// line by line.
+///
+/// Each line of stdout and stderr is sent to
+/// `tracing` and to [`Runtime::log`], and appended
+/// to the log file if one is given. The child's
+/// stdout and stderr must be piped. The full
+/// output is returned once the child exits.
+pub async fn stream_output(mut child: Child, runtime: &Runtime, log_file: Option<&Path>) -> std::io::Result<std::process::Output> {
+    let mut log_file = match log_file {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path).await?),
+        None => None
+    };
+
+    let mut stdout = Stream::new(child.stdout.take());
+    let mut stderr = Stream::new(child.stderr.take());
+
+    while !(stdout.done && stderr.done) {
+        let (line, stream) = tokio::select! {
+            line = stdout.next_line(), if !stdout.done => (line?, "stdout"),
+            line = stderr.next_line(), if !stderr.done => (line?, "stderr")
+        };
+
+        let Some(line) = line else {
+            continue;
+        };
+
+        tracing::info!(stream, "{}", line);
+
+        if let Some(file) = &mut log_file {
+            file.write_all(line.as_bytes()).await?;
+            file.write_all(b"\n").await?;
+        }
+
+        runtime.log(line);
+    }
+
+    Ok(std::process::Output {
+        status: child.wait().await?,
+        stdout: stdout.buffer,
+        stderr: stderr.buffer
+    })
+}
+
+/// One output pipe of a child process.
+struct Stream<R> {
+    reader: Option<BufReader<R>>,
+    buffer: Vec<u8>,
+    /// Where the line being read starts in the
+    /// buffer.
+    line_start: usize,
+    done: bool
+}
+
+impl<R: AsyncRead + Unpin> Stream<R> {
+    fn new(reader: Option<R>) -> Self {
+        Self {
+            done: reader.is_none(),
+            reader: reader.map(BufReader::new),
+            buffer: Vec::new(),
+            line_start: 0
+        }
+    }
+
+    /// Read the next line, or `None` at the end of
+    /// the stream.
+    ///
+    /// This is safe to cancel, as in `select!`.
+    /// Bytes read before the cancellation stay in
+    /// the buffer, and the next call finishes the
+    /// line they belong to.
+    async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        let Some(reader) = &mut self.reader else {
+            return Ok(None)
+        };
+
+        let read = reader.read_until(b'\n', &mut self.buffer).await.inspect_err(|_| {
+            // A broken pipe will not recover, so stop
+            // reading instead of failing forever.
+            self.done = true;
+        })?;
+
+        if read == 0 {
+            self.done = true;
+
+            // A cancelled read may have left the start
+            // of a last line with no newline.
+            if self.line_start == self.buffer.len() {
+                return Ok(None)
+            }
+        }
+
+        let line = String::from_utf8_lossy(&self.buffer[self.line_start..])
+            .trim_end_matches(['\n', '\r'])
+            .to_string();
+
+        self.line_start = self.buffer.len();
+
+        Ok(Some(line))
+    }
+
+    /// Forget the lines that have been returned,
+    /// keeping the start of a line still being
+    /// read.
+    fn discard_lines(&mut self) {
+        self.buffer.drain(..self.line_start);
+        self.line_start = 0;
+    }
+}
+
+fn command_failed(what: &str, name: &str, status: ExitStatus, stderr: &[u8]) -> Error {
+    let tail = stderr_tail(&String::from_utf8_lossy(stderr));
+
+    let detail = if tail.is_empty() {
+        format!("Failed to run command: {}", name)
+    } else {
+        format!("Failed to run command: {}\n{}", name, tail)
+    };
+
+    let message = match (status.code(), status.signal()) {
+        (Some(code), _) => format!("{} exited with unexpected status {}", what, code),
+        (None, Some(signal)) => format!("{} was killed by signal {}", what, signal),
+        (None, None) => format!("{} exited with unexpected status: {}", what, status)
+    };
+
+    let err = Error::new(ErrorKind::CommandFailed, message).with_detail(detail);
+
+    match status.code() {
+        Some(code) => err.with_exit_code(code),
+        None => err
+    }
+}
+
+fn stderr_tail(stderr: &str) -> String {
+    let lines = stderr.lines().collect::<Vec<_>>();
+    let start = lines.len().saturating_sub(STDERR_TAIL_LINES);
+
+    lines[start..].join("\n")
+}
+
+/// How a [`Spawn`] action knows that its process
+/// is ready.
+#[derive(Debug, Clone)]
+pub enum Readiness {
+    /// A line of stdout or stderr matches.
+    LogLine(Regex),
+    /// A TCP connection to an address, like
+    /// `127.0.0.1:8080`, succeeds.
+    Tcp(String),
+    /// A path exists.
+    Path(PathBuf)
+}
+
+/// Starts a long-running process, such as a server
+/// for integration tests.
+///
+/// The action finishes once the process is ready,
+/// and returns its PID. The process keeps running
+/// while the rest of the workflow runs. It is
+/// terminated when the workflow ends, on
+/// rollback, or when the action is dropped, along
+/// with any processes it started.
+pub struct Spawn {
+    command: Vec<Input<String>>,
+    env: Vec<(String, Input<String>)>,
+    cwd: Option<PathBuf>,
+    ready: Option<Readiness>,
+    ready_timeout: Duration,
+    child: Arc<Mutex<Option<Child>>>
+}
+
+impl Spawn {
+    pub fn new(command: Vec<Input<String>>) -> Self {
+        Self {
+            command,
+            env: Vec::new(),
+            cwd: None,
+            ready: None,
+            ready_timeout: Duration::from_secs(30),
+            child: Arc::new(Mutex::new(None))
+        }
+    }
+
+    pub fn env<K: Into<String>, V: Into<Input<String>>>(&mut self, key: K, value: V) -> &mut Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn cwd<P: Into<PathBuf>>(&mut self, cwd: P) -> &mut Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Wait for the process to be ready before
+    /// finishing. Without this, the action finishes
+    /// as soon as the process has started.
+    pub fn ready(&mut self, ready: Readiness) -> &mut Self {
+        self.ready = Some(ready);
+        self
+    }
+
+    /// Set how long to wait for the process to be
+    /// ready. Defaults to 30 seconds.
+    pub fn ready_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    async fn start(&self, runtime: &Runtime) -> Result<(String, Child), Error> {
+        let argv = resolve_argv(&self.command, runtime.clone()).await?;
+        let Some(name) = argv.first().cloned() else {
+            return Err(Error::new(ErrorKind::ActionFailed, "Empty command"))
+        };
+
+        let mut command = TokioCommand::new(&name);
+        command
+            .args(argv.into_iter().skip(1).collect::<Vec<String>>())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+
+        for (key, value) in &self.env {
+            command.env(key, resolve_input(value, runtime).await?);
+        }
+
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        let child = command.spawn()
+            .map_err(|e| Error::new(ErrorKind::Io, format!("Internal spawn error: {}", e))
+                .with_detail(format!("Failed to spawn command: {}", name))
+                .with_source(e)
+            )?;
+
+        Ok((name, child))
+    }
+
+    /// Check whether the process is ready, or has
+    /// exited.
+    async fn is_ready(&self, name: &str, matched: &mut oneshot::Receiver<()>, remaining: Duration) -> Result<bool, Error> {
+        if let Some(child) = self.child.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(Error::new(ErrorKind::CommandFailed, format!("Process exited before it was ready: {}", status))
+                    .with_detail(format!("Failed to start: {}", name))
+                    .with_exit_code(status.code().unwrap_or(1))
+                )
+            }
+        }
+
+        Ok(match &self.ready {
+            None => true,
+            Some(Readiness::LogLine(_)) => matched.try_recv().is_ok(),
+            Some(Readiness::Tcp(address)) => {
+                let connect = TcpStream::connect(address);
+
+                tokio::time::timeout(remaining.min(READY_CONNECT_TIMEOUT), connect)
+                    .await
+                    .is_ok_and(|result| result.is_ok())
+            },
+            Some(Readiness::Path(path)) => path.exists()
+        })
+    }
+}
+
+#[async_trait]
+impl Action for Spawn {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
+        Ok(Probe {
+            needs_run: true,
+            can_rollback: true
+        })
+    }
+
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+        if matches!(op, Operation::Rollback) {
+            let child = self.child.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+            if let Some(child) = child {
+                terminate(child).await;
+            }
+
+            return Ok(None)
+        }
+
+        // A retry replaces the process, so the one
+        // from the last attempt must not keep running.
+        let previous = self.child.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+        if let Some(previous) = previous {
+            terminate(previous).await;
+        }
+
+        let (name, mut child) = self.start(&runtime).await?;
+        let pid = child.id().unwrap_or_default();
+
+        let pattern = match &self.ready {
+            Some(Readiness::LogLine(pattern)) => Some(pattern.clone()),
+            _ => None
+        };
+
+        let (matched_tx, mut matched) = oneshot::channel();
+        tokio::spawn(forward_output(
+            Stream::new(child.stdout.take()),
+            Stream::new(child.stderr.take()),
+            runtime.clone(),
+            pattern,
+            matched_tx
+        ));
+
+        *self.child.lock().unwrap_or_else(PoisonError::into_inner) = Some(child);
+
+        let registered = self.child.clone();
+        runtime.add_finalizer(format!("Stop {}", name), move || async move {
+            let child = registered.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+            if let Some(child) = child {
+                terminate(child).await;
+            }
+
+            Ok(())
+        });
+
+        let started = Instant::now();
+
+        while !self.is_ready(&name, &mut matched, self.ready_timeout.saturating_sub(started.elapsed())).await? {
+            if started.elapsed() >= self.ready_timeout {
+                let child = self.child.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+                if let Some(child) = child {
+                    terminate(child).await;
+                }
+
+                return Err(Error::new(ErrorKind::TimedOut, format!("Process was not ready after {:?}", self.ready_timeout))
+                    .with_detail(format!("Failed to start: {}", name))
+                )
+            }
+
+            tokio::time::sleep(READY_POLL_INTERVAL).await;
+        }
+
+        Ok(Some(Output::Integer(pid.into())))
+    }
+
+    fn display_name(&self) -> String {
+        format!("Spawn: {}", match self.command.first() {
+            Some(Input::Static(value)) => value,
+            Some(Input::Dynamic(_)) => "<dynamic>",
             None => "<empty>"
         })
     }
-}
\ No newline at end of file
+}
+
+/// Send the output of a spawned process to
+/// `tracing` and the runtime until it exits,
+/// watching for a readiness pattern.
+async fn forward_output(
+    mut stdout: Stream<ChildStdout>,
+    mut stderr: Stream<ChildStderr>,
+    runtime: Runtime,
+    pattern: Option<Regex>,
+    matched: oneshot::Sender<()>
+) {
+    let mut matched = Some(matched);
+
+    while !(stdout.done && stderr.done) {
+        let (line, stream) = tokio::select! {
+            line = stdout.next_line(), if !stdout.done => (line, "stdout"),
+            line = stderr.next_line(), if !stderr.done => (line, "stderr")
+        };
+
+        // Only unfinished lines are kept, so the
+        // process can run for as long as it likes.
+        stdout.discard_lines();
+        stderr.discard_lines();
+
+        let Ok(Some(line)) = line else {
+            continue;
+        };
+
+        if pattern.as_ref().is_some_and(|pattern| pattern.is_match(&line)) {
+            if let Some(matched) = matched.take() {
+                let _ = matched.send(());
+            }
+        }
+
+        tracing::info!(stream, "{}", line);
+        runtime.log(line);
+    }
+}
+
+impl Drop for Spawn {
+    fn drop(&mut self) {
+        let child = self.child.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(child) = child.as_ref() {
+            signal_group(child, libc::SIGKILL);
+        }
+    }
+}
+
+/// Send a signal to the process group of a spawned
+/// process, so that its own children get it too.
+fn signal_group(child: &Child, signal: libc::c_int) {
+    if let Some(pid) = child.id() {
+        // SAFETY: `kill` has no memory safety
+        // requirements. The child has not been
+        // reaped, so its group still exists.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), signal);
+        }
+    }
+}
+
+/// Stop a process with `SIGTERM`, then `SIGKILL` if
+/// it is still running after a grace period.
+async fn terminate(mut child: Child) {
+    signal_group(&child, libc::SIGTERM);
+
+    if tokio::time::timeout(TERMINATE_GRACE_PERIOD, child.wait()).await.is_err() {
+        signal_group(&child, libc::SIGKILL);
+        let _ = child.wait().await;
+    }
+}
@@ -17,16 +17,16 @@ impl Sleep {
 
 #[async_trait]
 impl Action for Sleep {
-    async fn probe(&self, _runtime: Runtime) -> Result<Probe, ActionError> {
+    async fn probe(&self, _runtime: Runtime) -> Result<Probe, Error> {
         Ok(Probe {
             needs_run: true,
             can_rollback: false
         })
     }
 
-    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<ActionOutput>, ActionError> {
+    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if matches!(op, Operation::Rollback) {
-            return Err(ActionError::OperationNotSupported)
+            return Err(ErrorKind::OperationNotSupported.into())
         }
 
         sleep(self.duration).await;
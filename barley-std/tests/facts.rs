@@ -0,0 +1,33 @@
+use barley_runtime::condition::IfVariable;
+use barley_runtime::testing::MockRuntime;
+use barley_runtime::{Action, Condition, ErrorKind, Operation, RuntimeBuilder};
+use barley_std::facts::{Facts, GatherFacts};
+
+
+#[tokio::test(flavor = "current_thread")]
+async fn numeric_facts_are_u64() {
+    let builder = RuntimeBuilder::new()
+        .add_action(GatherFacts::new().into())
+        .await;
+
+    let facts = builder.get_state::<Facts>().expect("facts were not gathered");
+    let runtime = builder.build();
+
+    assert_eq!(*runtime.get_variable::<u64>("facts.cpus").unwrap(), facts.cpus);
+    assert_eq!(*runtime.get_variable::<u64>("facts.memory.total").unwrap(), facts.memory.total);
+    assert!(runtime.get_variable::<usize>("facts.cpus").is_none());
+
+    let condition = IfVariable::equals("facts.cpus", facts.cpus);
+    assert!(condition.evaluate(runtime).await.unwrap());
+}
+
+#[tokio::test]
+async fn missing_facts_fail_the_action() {
+    let action = GatherFacts::new();
+    let runtime = MockRuntime::new().build();
+
+    assert!(action.probe(runtime.clone()).await.unwrap().needs_run);
+
+    let err = action.run(runtime, Operation::Perform).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::StateNotLoaded);
+}
@@ -0,0 +1,379 @@
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::process::Command;
+
+use barley_runtime::prelude::*;
+use barley_runtime::testing::MockRuntime;
+use regex::Regex;
+use barley_std::fs::{
+    Copy, CreateDir, EnsureLine, ManagedBlock, Move,
+    ReplaceRegex, SetOwner, SetPermissions, Symlink,
+    WriteFile
+};
+
+
+fn id(flag: &str) -> String {
+    let output = Command::new("id").arg(flag).output().unwrap();
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[tokio::test]
+async fn owners_are_looked_up_by_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "").unwrap();
+
+    let owner = SetOwner::new(&path).user(id("-un")).group(id("-gn"));
+    let probe = owner.probe(MockRuntime::new().build()).await.unwrap();
+
+    assert!(!probe.needs_run);
+}
+
+#[tokio::test]
+async fn unknown_owners_fail() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let owner = SetOwner::new(dir.path()).user("no-such-user-for-barley");
+    let err = owner.probe(MockRuntime::new().build()).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+}
+
+/// Check that an action needs to run, run it, and
+/// check that it is then up to date.
+async fn converge<A: Action>(action: &A) {
+    assert!(action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert!(!action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+}
+
+#[tokio::test]
+async fn directories_are_created_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a/b/c");
+
+    converge(&CreateDir::new(&path)).await;
+    assert!(path.is_dir());
+}
+
+#[tokio::test]
+async fn copies_follow_their_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+
+    std::fs::create_dir_all(from.join("nested")).unwrap();
+    std::fs::write(from.join("nested/file"), "one").unwrap();
+    std::os::unix::fs::symlink("nested/file", from.join("link")).unwrap();
+
+    let action = Copy::new(&from, &to);
+    converge(&action).await;
+
+    assert_eq!(std::fs::read_to_string(to.join("nested/file")).unwrap(), "one");
+    assert_eq!(std::fs::read_link(to.join("link")).unwrap(), std::path::Path::new("nested/file"));
+
+    // A change to the source needs another copy.
+    std::fs::write(from.join("nested/file"), "two").unwrap();
+    converge(&action).await;
+
+    assert_eq!(std::fs::read_to_string(to.join("nested/file")).unwrap(), "two");
+}
+
+#[tokio::test]
+async fn symlinks_are_replaced_but_files_are_not() {
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("link");
+
+    std::os::unix::fs::symlink("old", &link).unwrap();
+    converge(&Symlink::new("new", &link)).await;
+    assert_eq!(std::fs::read_link(&link).unwrap(), std::path::Path::new("new"));
+
+    let file = dir.path().join("file");
+    std::fs::write(&file, "keep").unwrap();
+
+    let err = Symlink::new("new", &file)
+        .run(MockRuntime::new().build(), Operation::Perform)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep");
+}
+
+#[tokio::test]
+async fn permissions_are_set_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    converge(&SetPermissions::new(&path, 0o600)).await;
+    assert_eq!(mode(&path), 0o600);
+}
+
+#[tokio::test]
+async fn owners_are_set_once() {
+    if id("-u") != "0" {
+        // Only root can give files away.
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "").unwrap();
+
+    converge(&SetOwner::new(&path).user(65534).group(65534)).await;
+
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!((metadata.uid(), metadata.gid()), (65534, 65534));
+}
+
+#[tokio::test]
+async fn move_is_done_once_the_destination_exists() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+    let action = Move::new(&from, &to);
+
+    // Neither path exists, so the move has not
+    // happened and running it must fail.
+    assert!(action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    assert!(action.run(MockRuntime::new().build(), Operation::Perform).await.is_err());
+
+    std::fs::write(&from, "moved").unwrap();
+    assert!(action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert!(!action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    assert_eq!(std::fs::read_to_string(&to).unwrap(), "moved");
+}
+
+fn mode(path: &std::path::Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o7777
+}
+
+#[tokio::test]
+async fn identical_files_are_not_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "content").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    let action = WriteFile::new_static(&path, "content").mode(0o640);
+    assert!(!action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+}
+
+#[tokio::test]
+async fn a_different_mode_needs_a_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "content").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let action = WriteFile::new_static(&path, "content").mode(0o600);
+    assert!(action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+}
+
+#[tokio::test]
+async fn rollback_restores_content_and_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "old").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    let action = WriteFile::new_static(&path, "new").mode(0o600);
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+    assert_eq!(mode(&path), 0o600);
+
+    action.run(MockRuntime::new().build(), Operation::Rollback).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+    assert_eq!(mode(&path), 0o640);
+}
+
+#[tokio::test]
+async fn rollback_removes_a_new_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    let action = WriteFile::new_static(&path, "new");
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert!(path.exists());
+
+    action.run(MockRuntime::new().build(), Operation::Rollback).await.unwrap();
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn symlinks_are_written_through() {
+    let dir = tempfile::tempdir().unwrap();
+    let (target, link) = (dir.path().join("target"), dir.path().join("link"));
+    std::fs::write(&target, "old").unwrap();
+    std::os::unix::fs::symlink("target", &link).unwrap();
+
+    let action = WriteFile::new_static(&link, "new");
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[tokio::test]
+async fn a_line_that_does_not_match_its_pattern_is_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sshd_config");
+    std::fs::write(&path, "Port 22\nPermitRootLogin yes\n").unwrap();
+
+    let action = EnsureLine::present(&path, "PermitRootLogin no")
+        .matching(Regex::new("^PermitRootLogin yes$").unwrap());
+
+    assert!(action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert!(!action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Port 22\nPermitRootLogin no\n");
+}
+
+#[tokio::test]
+async fn unmatched_block_markers_fail() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let content = "# BEGIN barley\nuser line\n";
+    std::fs::write(&path, content).unwrap();
+
+    let action = ManagedBlock::new(&path, "managed");
+    let err = action.probe(MockRuntime::new().build()).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+
+    assert!(action.run(MockRuntime::new().build(), Operation::Perform).await.is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+    // A second BEGIN means the first one is not
+    // part of the pair.
+    std::fs::write(&path, "# BEGIN barley\nuser line\n# BEGIN barley\nold\n# END barley\n").unwrap();
+    assert!(action.run(MockRuntime::new().build(), Operation::Perform).await.is_err());
+}
+
+#[tokio::test]
+async fn managed_blocks_are_replaced_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "before\n# BEGIN barley\nold\n# END barley\nafter\n").unwrap();
+
+    let action = ManagedBlock::new(&path, "new");
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "before\n# BEGIN barley\nnew\n# END barley\nafter\n");
+    assert!(!action.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+}
+
+#[tokio::test]
+async fn rollback_through_a_dangling_symlink_keeps_the_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let (target, link) = (dir.path().join("target"), dir.path().join("link"));
+    std::os::unix::fs::symlink("target", &link).unwrap();
+
+    let action = WriteFile::new_static(&link, "new");
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+
+    action.run(MockRuntime::new().build(), Operation::Rollback).await.unwrap();
+
+    assert!(!target.exists());
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+}
+
+#[tokio::test]
+async fn setgid_survives_a_new_group() {
+    if id("-u") != "0" {
+        // Only root can give files away.
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    let action = WriteFile::new_static(&path, "content").mode(0o2775).group(65534);
+    converge(&action).await;
+
+    assert_eq!(mode(&path), 0o2775);
+}
+
+#[tokio::test]
+async fn absent_lines_are_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hosts");
+    std::fs::write(&path, "127.0.0.1 localhost\n10.0.0.1 old\n10.0.0.2 older\n").unwrap();
+
+    converge(&EnsureLine::absent(&path, "10.0.0.1 old")).await;
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "127.0.0.1 localhost\n10.0.0.2 older\n");
+
+    converge(&EnsureLine::absent(&path, "").matching(Regex::new("^10\\.").unwrap())).await;
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "127.0.0.1 localhost\n");
+}
+
+#[tokio::test]
+async fn patterns_are_replaced_everywhere() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config");
+    std::fs::write(&path, "port = 80\nadmin_port = 81\n").unwrap();
+
+    let action = ReplaceRegex::new(&path, Regex::new(r"(?m)^(\w*port) = 8(\d)$").unwrap(), "$1 = 808$2");
+    converge(&action).await;
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "port = 8080\nadmin_port = 8081\n");
+}
+
+#[tokio::test]
+async fn edits_are_rolled_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let original = "keep\nchange me\n";
+    std::fs::write(&path, original).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    let line = EnsureLine::present(&path, "added");
+    let regex = ReplaceRegex::new(&path, Regex::new("change me").unwrap(), "changed");
+    let block = ManagedBlock::new(&path, "managed");
+
+    for action in [&line as &dyn Action, &regex, &block] {
+        action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    }
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "keep\nchanged\nadded\n# BEGIN barley\nmanaged\n# END barley\n"
+    );
+
+    // Each action restores the file as it found it,
+    // so they are rolled back in reverse.
+    for action in [&block as &dyn Action, &regex, &line] {
+        action.run(MockRuntime::new().build(), Operation::Rollback).await.unwrap();
+    }
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    assert_eq!(mode(&path), 0o640);
+
+    // An edit that created the file removes it.
+    let new = dir.path().join("new");
+    let action = EnsureLine::present(&new, "line");
+    action.run(MockRuntime::new().build(), Operation::Perform).await.unwrap();
+    action.run(MockRuntime::new().build(), Operation::Rollback).await.unwrap();
+
+    assert!(!new.exists());
+}
+
+#[tokio::test]
+async fn windows_line_endings_are_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "one\r\ntwo\r\n").unwrap();
+
+    converge(&EnsureLine::present(&path, "three")).await;
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\r\ntwo\r\nthree\r\n");
+
+    converge(&ManagedBlock::new(&path, "managed")).await;
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "one\r\ntwo\r\nthree\r\n# BEGIN barley\r\nmanaged\r\n# END barley\r\n"
+    );
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use barley_runtime::prelude::*;
+use barley_runtime::testing::MockRuntime;
+use barley_std::process::{stream_output, Capture, CheckCodes, Command, Readiness, Spawn};
+use regex::Regex;
+
+
+#[tokio::test]
+async fn partial_lines_survive_other_streams() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("output.log");
+
+    // stdout stops halfway through a line while
+    // stderr writes whole lines, so the stdout read
+    // is cancelled with the start of a line read.
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg("printf 'first '; sleep 0.1; echo one >&2; sleep 0.1; echo two >&2; sleep 0.1; echo half; printf tail")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let output = stream_output(child, &MockRuntime::new().build(), Some(&log)).await.unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"first half\ntail");
+
+    let logged = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(logged, "one\ntwo\nfirst half\ntail\n");
+}
+
+#[tokio::test]
+async fn spawn_sees_ready_lines_split_by_other_output() {
+    let mut spawn = Spawn::new(vec![
+        Input::new_static("sh".to_string()),
+        Input::new_static("-c".to_string()),
+        Input::new_static(
+            "printf rea; sleep 0.1; echo noise >&2; sleep 0.1; echo noise >&2; sleep 0.1; echo dy; exec sleep 30".to_string()
+        )
+    ]);
+
+    spawn
+        .ready(Readiness::LogLine(Regex::new("^ready$").unwrap()))
+        .ready_timeout(Duration::from_secs(5));
+
+    let started = Instant::now();
+
+    RuntimeBuilder::new()
+        .add_action(spawn.into())
+        .await
+        .build()
+        .perform()
+        .await
+        .unwrap();
+
+    // The process is stopped when the run ends,
+    // instead of sleeping on.
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn empty_commands_fail() {
+    let runtime = MockRuntime::new().build();
+
+    let err = Command::new(Vec::new())
+        .run(runtime.clone(), Operation::Perform)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+
+    let err = Spawn::new(Vec::new())
+        .run(runtime, Operation::Perform)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ActionFailed);
+}
+
+fn argv(args: &[&str]) -> Vec<Input<String>> {
+    args.iter().map(|arg| Input::new_static(arg.to_string())).collect()
+}
+
+async fn run(command: &Command) -> Result<Option<Output>, Error> {
+    command.run(MockRuntime::new().build(), Operation::Perform).await
+}
+
+/// Run a command that captures its output, and
+/// return the captured map.
+async fn captured(command: &Command) -> BTreeMap<String, Output> {
+    match run(command).await.unwrap() {
+        Some(Output::Map(map)) => map,
+        output => panic!("expected a map, got {output:?}")
+    }
+}
+
+async fn stdout(command: &mut Command) -> String {
+    command.capture(Capture::Text);
+
+    match captured(command).await.remove("stdout") {
+        Some(Output::String(stdout)) => stdout,
+        output => panic!("expected a string, got {output:?}")
+    }
+}
+
+#[tokio::test]
+async fn output_is_only_returned_when_captured() {
+    let mut command = Command::new(argv(&["sh", "-c", "echo out; echo err >&2"]));
+    assert!(run(&command).await.unwrap().is_none());
+
+    command.capture(Capture::Text);
+    let map = captured(&command).await;
+
+    assert_eq!(map["stdout"], Output::String("out\n".to_string()));
+    assert_eq!(map["stderr"], Output::String("err\n".to_string()));
+    assert_eq!(map["code"], Output::Integer(0));
+
+    command.capture(Capture::Lines);
+    let map = captured(&command).await;
+
+    assert_eq!(map["stdout"], Output::List(vec!["out".into()]));
+}
+
+#[tokio::test]
+async fn environment_is_set_and_cleared() {
+    let mut command = Command::new(argv(&["sh", "-c", "printf %s \"$GREETING\""]));
+    command.env("GREETING", "hello".to_string());
+    assert_eq!(stdout(&mut command).await, "hello");
+
+    let mut command = Command::new(argv(&["/usr/bin/env"]));
+    command.env_clear().env("ONLY", "this".to_string());
+    assert_eq!(stdout(&mut command).await, "ONLY=this\n");
+}
+
+#[tokio::test]
+async fn cwd_and_stdin_are_set() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut command = Command::new(argv(&["pwd"]));
+    command.cwd(dir.path());
+    assert_eq!(stdout(&mut command).await.trim(), dir.path().canonicalize().unwrap().to_str().unwrap());
+
+    let mut command = Command::new(argv(&["cat"]));
+    command.stdin("piped".to_string());
+    assert_eq!(stdout(&mut command).await, "piped");
+
+    // Without stdin, the command reads nothing
+    // instead of waiting for the engine's stdin.
+    let mut command = Command::new(argv(&["cat"]));
+    assert_eq!(stdout(&mut command).await, "");
+}
+
+#[tokio::test]
+async fn commands_run_as_another_user() {
+    let mut whoami = Command::new(argv(&["id", "-u"]));
+
+    if stdout(&mut whoami).await.trim() != "0" {
+        // Only root can switch users.
+        return;
+    }
+
+    let mut command = Command::new(argv(&["id", "-u"]));
+    command.uid(65534).gid(65534);
+    assert_eq!(stdout(&mut command).await.trim(), "65534");
+}
+
+#[tokio::test]
+async fn shell_mode_runs_scripts() {
+    let mut command = Command::shell("echo one | tr a-z A-Z".to_string());
+    assert_eq!(stdout(&mut command).await, "ONE\n");
+
+    let mut command = Command::shell("printf %s \"$0\"".to_string());
+    command.interpreter(["bash", "-c"]);
+    assert_eq!(stdout(&mut command).await, "bash");
+
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("marker");
+
+    let mut command = Command::shell(format!("touch {}", marker.display()));
+    command.check_shell(format!("test -e {}", marker.display()));
+
+    assert!(command.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    run(&command).await.unwrap();
+    assert!(!command.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+}
+
+#[tokio::test]
+async fn success_codes_decide_failure() {
+    let mut command = Command::shell("exit 3".to_string());
+    command.success_codes([0, 3]);
+    run(&command).await.unwrap();
+
+    let mut command = Command::shell("exit 0".to_string());
+    command.success_codes([1]);
+    let err = run(&command).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::CommandFailed);
+    assert_eq!(err.message(), "Command exited with unexpected status 0");
+    assert_eq!(err.exit_code(), Some(0));
+}
+
+#[tokio::test]
+async fn signals_are_reported() {
+    let command = Command::shell("kill -9 $$".to_string());
+    let err = run(&command).await.unwrap_err();
+
+    assert_eq!(err.message(), "Command was killed by signal 9");
+    assert_eq!(err.exit_code(), None);
+}
+
+#[tokio::test]
+async fn check_codes_map_to_probes() {
+    let probe = |code: i32| async move {
+        let mut command = Command::shell("true".to_string());
+        command
+            .check_shell(format!("exit {code}"))
+            .check_codes(CheckCodes {
+                up_to_date: vec![0],
+                needs_run: vec![2]
+            });
+
+        command.probe(MockRuntime::new().build()).await
+    };
+
+    assert!(!probe(0).await.unwrap().needs_run);
+    assert!(probe(2).await.unwrap().needs_run);
+
+    let err = probe(5).await.unwrap_err();
+    assert_eq!(err.message(), "Check exited with unexpected status 5");
+}
+
+#[tokio::test]
+async fn creates_and_removes_guard_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    let mut creates = Command::shell("true".to_string());
+    creates.creates(&path);
+
+    let mut removes = Command::shell("true".to_string());
+    removes.removes(&path);
+
+    assert!(creates.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    assert!(!removes.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+
+    std::fs::write(&path, "").unwrap();
+
+    assert!(!creates.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+    assert!(removes.probe(MockRuntime::new().build()).await.unwrap().needs_run);
+}
+
+fn sleeper() -> Spawn {
+    Spawn::new(argv(&["sleep", "30"]))
+}
+
+fn is_alive(pid: i64) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[tokio::test]
+async fn unanswered_connections_respect_the_ready_timeout() {
+    // With a backlog of zero and one connection
+    // waiting, new connections are never answered.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    // SAFETY: the socket is open for as long as the
+    // listener lives.
+    assert_eq!(unsafe { libc::listen(listener.as_raw_fd(), 0) }, 0);
+    let _waiting = TcpStream::connect(address).unwrap();
+
+    let mut spawn = sleeper();
+    spawn
+        .ready(Readiness::Tcp(address.to_string()))
+        .ready_timeout(Duration::from_millis(300));
+
+    let started = Instant::now();
+    let err = spawn.run(MockRuntime::new().build(), Operation::Perform).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn retries_stop_the_previous_process() {
+    let spawn = sleeper();
+    let runtime = MockRuntime::new().build();
+
+    let Some(Output::Integer(first)) = spawn.run(runtime.clone(), Operation::Perform).await.unwrap() else {
+        panic!("Spawn did not return a PID");
+    };
+
+    assert!(is_alive(first));
+
+    let Some(Output::Integer(second)) = spawn.run(runtime.clone(), Operation::Perform).await.unwrap() else {
+        panic!("Spawn did not return a PID");
+    };
+
+    assert!(!is_alive(first));
+    assert!(is_alive(second));
+
+    spawn.run(runtime, Operation::Rollback).await.unwrap();
+    assert!(!is_alive(second));
+}
@@ -1,5 +1,7 @@
 use barley_runtime::prelude::*;
+use barley_std::process::stream_output;
 use async_trait::async_trait;
+use std::process::Stdio;
 use tokio::process::Command;
 
 
@@ -8,7 +10,7 @@ pub struct AptUpdate;
 
 impl AptUpdate {
     pub fn new() -> Self {
-        Self::default()
+        Self
     }
 }
 
@@ -21,25 +23,33 @@ impl Action for AptUpdate {
         })
     }
 
-    async fn run(&self, _runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
+    async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if op == Operation::Rollback {
-            return Err(Error::OperationNotSupported);
+            return Err(ErrorKind::OperationNotSupported.into());
         }
 
-        let cmd = Command::new("apt-get")
+        let io_error = |e: std::io::Error| Error::new(ErrorKind::Io, "Failed to run `apt-get update`")
+            .with_detail(e.to_string())
+            .with_source(e);
+
+        let child = Command::new("apt-get")
             .arg("update")
-            .output()
-            .await
-            .map_err(|e| Error::ActionFailed(
-                "Failed to run `apt-get update`".to_string(),
-                e.to_string()
-            ))?;
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(io_error)?;
+
+        let cmd = stream_output(child, &runtime, None).await.map_err(io_error)?;
         
         if !cmd.status.success() {
-            return Err(Error::ActionFailed(
-                "`apt-get update` returned an error".to_string(),
-                String::from_utf8_lossy(&cmd.stderr).to_string()
-            ))
+            let mut err = Error::new(ErrorKind::CommandFailed, "`apt-get update` returned an error")
+                .with_detail(String::from_utf8_lossy(&cmd.stderr).to_string());
+
+            if let Some(code) = cmd.status.code() {
+                err = err.with_exit_code(code);
+            }
+
+            return Err(err)
         }
 
         Ok(None)
@@ -73,12 +83,12 @@ impl AptInstall {
                 Input::Static(s) => names.push(s.clone()),
                 Input::Dynamic(d) => {
                     let output = runtime.get_output(d.clone()).await
-                        .ok_or(Error::NoActionReturn)?;
+                        .ok_or(Error::from(ErrorKind::NoActionReturn))?;
 
                     if let Output::String(s) = output {
                         names.push(s);
                     } else {
-                        return Err(Error::WrongOutputType);
+                        return Err(ErrorKind::WrongOutputType.into());
                     }
                 }
             }
@@ -99,10 +109,10 @@ impl Action for AptInstall {
                 .arg(&name)
                 .output()
                 .await
-                .map_err(|e| Error::ActionFailed(
-                    format!("Failed to run `dpkg -s {}`", name),
-                    e.to_string()
-                ))?;
+                .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to run `dpkg -s {}`", name))
+                    .with_detail(e.to_string())
+                    .with_source(e)
+                )?;
             
             if !cmd.status.success() {
                 return Ok(Probe {
@@ -129,27 +139,35 @@ impl Action for AptInstall {
 
     async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if op == Operation::Rollback {
-            return Err(Error::OperationNotSupported);
+            return Err(ErrorKind::OperationNotSupported.into());
         }
 
-        let names = self.get_package_names(runtime).await?;
+        let names = self.get_package_names(runtime.clone()).await?;
+
+        let io_error = |e: std::io::Error| Error::new(ErrorKind::Io, format!("Failed to run `apt-get install {}`", names.join(" ")))
+            .with_detail(e.to_string())
+            .with_source(e);
 
-        let cmd = Command::new("apt-get")
+        let child = Command::new("apt-get")
             .arg("install")
             .arg("-y")
             .args(&names)
-            .output()
-            .await
-            .map_err(|e| Error::ActionFailed(
-                format!("Failed to run `apt-get install {}`", names.join(" ")),
-                e.to_string()
-            ))?;
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(io_error)?;
+
+        let cmd = stream_output(child, &runtime, None).await.map_err(io_error)?;
         
         if !cmd.status.success() {
-            return Err(Error::ActionFailed(
-                "`apt-get install` returned an error".to_string(),
-                String::from_utf8_lossy(&cmd.stderr).to_string()
-            ))
+            let mut err = Error::new(ErrorKind::CommandFailed, "`apt-get install` returned an error")
+                .with_detail(String::from_utf8_lossy(&cmd.stderr).to_string());
+
+            if let Some(code) = cmd.status.code() {
+                err = err.with_exit_code(code);
+            }
+
+            return Err(err)
         }
 
         Ok(None)
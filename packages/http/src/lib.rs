@@ -29,35 +29,35 @@ impl Action for HttpGet {
 
     async fn run(&self, runtime: Runtime, op: Operation) -> Result<Option<Output>, Error> {
         if op == Operation::Rollback {
-            return Err(Error::OperationNotSupported);
+            return Err(ErrorKind::OperationNotSupported.into());
         }
 
         let url = match self.url {
             Input::Static(ref s) => s.clone(),
             Input::Dynamic(ref d) => {
                 let out = runtime.get_output(d.clone()).await
-                    .ok_or(Error::NoActionReturn)?;
+                    .ok_or(Error::from(ErrorKind::NoActionReturn))?;
 
                 if let Output::String(s) = out {
                     s
                 } else {
-                    return Err(Error::WrongOutputType);
+                    return Err(ErrorKind::WrongOutputType.into());
                 }
             }
         };
 
         let resp = ureq::get(&url)
             .call()
-            .map_err(|e| Error::ActionFailed(
-                format!("Failed to GET {}", url),
-                e.to_string()
-            ))?;
+            .map_err(|e| Error::action_failed(format!("Failed to GET {}", url))
+                .with_detail(e.to_string())
+                .with_source(e)
+            )?;
 
         let body = resp.into_string()
-            .map_err(|e| Error::ActionFailed(
-                format!("Failed to read response body from {}", url),
-                e.to_string()
-            ))?;
+            .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to read response body from {}", url))
+                .with_detail(e.to_string())
+                .with_source(e)
+            )?;
         
         Ok(Some(Output::String(body)))
     }